@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// How the benchmark workers are distributed over the cpus.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PinPolicy {
+    /// Fill each physical core, including its SMT siblings, before moving to the next one
+    Compact,
+    /// Spread over packages and physical cores first, SMT siblings are used last
+    Scatter,
+    /// Explicit cpu list, assigned round-robin to the workers
+    List(Vec<usize>),
+}
+
+impl FromStr for PinPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "compact" => Ok(PinPolicy::Compact),
+            "scatter" => Ok(PinPolicy::Scatter),
+            _ => {
+                let list = s.strip_prefix("list:").ok_or_else(|| {
+                    format!(
+                        "Unknown pin policy '{}', expected compact, scatter or list:<cpus>",
+                        s
+                    )
+                })?;
+                list.split(',')
+                    .map(|c| c.trim().parse().map_err(|_| format!("Invalid cpu '{}'", c)))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(PinPolicy::List)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct CpuTopology {
+    cpu: usize,
+    package: usize,
+    core: usize,
+}
+
+impl CpuTopology {
+    fn read(cpu: usize) -> Self {
+        let read_id = |name: &str| {
+            std::fs::read_to_string(format!(
+                "/sys/devices/system/cpu/cpu{}/topology/{}",
+                cpu, name
+            ))
+            .ok()
+            .and_then(|id| id.trim().parse().ok())
+        };
+        Self {
+            cpu,
+            package: read_id("physical_package_id").unwrap_or(0),
+            core: read_id("core_id").unwrap_or(cpu),
+        }
+    }
+}
+
+impl PinPolicy {
+    /// Returns the cpu of each of the `count` workers.
+    pub fn assign(&self, allowed: &[usize], count: usize) -> Result<Vec<usize>, String> {
+        let order = match self {
+            PinPolicy::Compact | PinPolicy::Scatter => {
+                let topology: Vec<_> = allowed.iter().map(|&c| CpuTopology::read(c)).collect();
+                if *self == PinPolicy::Compact {
+                    compact_order(&topology)
+                } else {
+                    scatter_order(&topology)
+                }
+            }
+            PinPolicy::List(cpus) => {
+                for &cpu in cpus {
+                    check_allowed(cpu, allowed)?;
+                }
+                cpus.clone()
+            }
+        };
+        if order.is_empty() {
+            return Err("No cpus to pin the workers to".to_string());
+        }
+        Ok((0..count).map(|i| order[i % order.len()]).collect())
+    }
+}
+
+/// Fails if `cpu` is not one of the `allowed` cpus, which pinning to would fail.
+pub fn check_allowed(cpu: usize, allowed: &[usize]) -> Result<(), String> {
+    match allowed.contains(&cpu) {
+        true => Ok(()),
+        false => Err(format!("Cpu {} is not available to this process", cpu)),
+    }
+}
+
+fn compact_order(cpus: &[CpuTopology]) -> Vec<usize> {
+    let mut cpus = cpus.to_vec();
+    cpus.sort_by_key(|c| (c.package, c.core, c.cpu));
+    cpus.iter().map(|c| c.cpu).collect()
+}
+
+fn scatter_order(cpus: &[CpuTopology]) -> Vec<usize> {
+    let mut cpus = cpus.to_vec();
+    cpus.sort_by_key(|c| (c.package, c.core, c.cpu));
+
+    // Rank every cpu by its SMT position inside the core and by the core position
+    // inside the package, then take the first sibling of every core, package by package.
+    let mut smt_ranks = HashMap::new();
+    let mut core_ranks = HashMap::new();
+    let mut cores_per_package = HashMap::new();
+    let mut ranked: Vec<_> = cpus
+        .iter()
+        .map(|c| {
+            let smt_rank = smt_ranks.entry((c.package, c.core)).or_insert(0);
+            *smt_rank += 1;
+            let core_rank = *core_ranks.entry((c.package, c.core)).or_insert_with(|| {
+                let cores = cores_per_package.entry(c.package).or_insert(0);
+                *cores += 1;
+                *cores
+            });
+            (*smt_rank, core_rank, c.package, c.cpu)
+        })
+        .collect();
+    ranked.sort();
+    ranked.into_iter().map(|(_, _, _, cpu)| cpu).collect()
+}
+
+/// The cpus this process is allowed to run on.
+pub fn allowed_cpus() -> Vec<usize> {
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        if libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set) != 0 {
+            return (0..num_cpus::get()).collect();
+        }
+        (0..libc::CPU_SETSIZE as usize)
+            .filter(|&cpu| libc::CPU_ISSET(cpu, &set))
+            .collect()
+    }
+}
+
+pub fn pin_current_thread(cpu: usize) -> std::io::Result<()> {
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(cpu, &mut set);
+        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::affinity::{compact_order, scatter_order, CpuTopology, PinPolicy};
+
+    // Two packages with two cores each and 2-way SMT, numbered like Linux does on x86
+    fn topology() -> Vec<CpuTopology> {
+        (0..8)
+            .map(|cpu| CpuTopology {
+                cpu,
+                package: (cpu / 2) % 2,
+                core: cpu % 2,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn orders() {
+        assert_eq!(compact_order(&topology()), vec![0, 4, 1, 5, 2, 6, 3, 7]);
+        assert_eq!(scatter_order(&topology()), vec![0, 2, 1, 3, 4, 6, 5, 7]);
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!("list:0,2,4".parse(), Ok(PinPolicy::List(vec![0, 2, 4])));
+        assert!("list:0,a".parse::<PinPolicy>().is_err());
+        assert!("spread".parse::<PinPolicy>().is_err());
+        assert!(PinPolicy::List(vec![3]).assign(&[0, 1], 1).is_err());
+    }
+}
@@ -0,0 +1,55 @@
+use crate::stop_signal::StopSignal;
+use crate::thread_stats::current_tid;
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+
+/// Threads spinning at another priority next to the workers of a run, competing with them
+/// for the cpus without being counted, as the rayon pools running alongside our pipelines do.
+pub struct BackgroundSpinners {
+    stop: StopSignal,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl BackgroundSpinners {
+    /// Starts `count` spinning threads with the given nice value, applied per thread on Linux.
+    /// Fails, with no thread left running, if a thread cannot take the nice value.
+    pub fn start(count: usize, nice: i32) -> Result<Self, String> {
+        let stop = StopSignal::new();
+        let (started, results) = mpsc::channel();
+        let threads = (0..count)
+            .map(|_| {
+                let stop = stop.clone();
+                let started = started.clone();
+                std::thread::spawn(move || {
+                    let tid = current_tid() as libc::id_t;
+                    let result = match unsafe { libc::setpriority(libc::PRIO_PROCESS, tid, nice) } {
+                        0 => Ok(()),
+                        _ => Err(std::io::Error::last_os_error()),
+                    };
+                    started.send(result).unwrap();
+                    while !stop.is_stopped() {
+                        std::hint::spin_loop();
+                    }
+                })
+            })
+            .collect();
+        let spinners = Self { stop, threads };
+        match results.iter().take(count).find_map(Result::err) {
+            None => Ok(spinners),
+            Some(err) => {
+                spinners.stop();
+                Err(format!(
+                    "Cannot set the nice value of the background spinners to {}: {}",
+                    nice, err
+                ))
+            }
+        }
+    }
+
+    pub fn stop(self) {
+        self.stop.stop();
+        for thread in self.threads {
+            thread.join().unwrap();
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use crate::benchmarks::{BenchOptions, Unit};
+use crate::fingerprint::Fingerprint;
+use crate::stats::mann_whitney_p_value;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+/// Differences with a higher p-value are attributed to noise.
+const SIGNIFICANCE: f64 = 0.05;
+
+/// Per-interval throughput of one benchmark run, identified by its name, thread count and options.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunResult {
+    pub benchmark: String,
+    pub threads: usize,
+    pub options: serde_json::Value,
+    pub unit: Unit,
+    pub ops_per_sec: Vec<f64>,
+}
+
+impl RunResult {
+    pub fn new(
+        benchmark: &str,
+        threads: usize,
+        options: &BenchOptions,
+        unit: Unit,
+        ops_per_sec: Vec<f64>,
+    ) -> Self {
+        Self {
+            benchmark: benchmark.to_string(),
+            threads,
+            options: serde_json::to_value(options).unwrap(),
+            unit,
+            ops_per_sec,
+        }
+    }
+
+    pub fn same_run(&self, other: &RunResult) -> bool {
+        self.benchmark == other.benchmark
+            && self.threads == other.threads
+            && self.options == other.options
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.ops_per_sec.iter().sum::<f64>() / self.ops_per_sec.len().max(1) as f64
+    }
+}
+
+/// Named set of run results stored as json in the results directory.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Baseline {
+    /// System the runs were done on, missing from the baselines saved before it was recorded
+    #[serde(default)]
+    pub fingerprint: Option<Fingerprint>,
+    pub runs: Vec<RunResult>,
+}
+
+impl Baseline {
+    /// File of the baseline, whose name cannot lead outside of the baseline directory.
+    fn path(dir: &Path, name: &str) -> Result<PathBuf, String> {
+        if name.is_empty() || name.contains(['/', '\\']) || name.contains("..") {
+            return Err(format!(
+                "Invalid baseline name '{}', expected no path separator or '..'",
+                name
+            ));
+        }
+        Ok(dir.join(format!("{}.json", name)))
+    }
+
+    pub fn load(dir: &Path, name: &str) -> Result<Self, String> {
+        let path = Self::path(dir, name)?;
+        let file = File::open(&path)
+            .map_err(|err| format!("Cannot open baseline {}: {}", path.display(), err))?;
+        serde_json::from_reader(BufReader::new(file))
+            .map_err(|err| format!("Invalid baseline {}: {}", path.display(), err))
+    }
+
+    /// Loads the baseline if it was saved before, failing if it cannot be read.
+    pub fn load_existing(dir: &Path, name: &str) -> Result<Option<Self>, String> {
+        match Self::path(dir, name)?.exists() {
+            true => Self::load(dir, name).map(Some),
+            false => Ok(None),
+        }
+    }
+
+    /// Saves the results, replacing the matching runs of an existing baseline with the same name,
+    /// which must have been recorded on a system with the same fingerprint, if it recorded one.
+    pub fn save(
+        dir: &Path,
+        name: &str,
+        fingerprint: &Fingerprint,
+        results: &[RunResult],
+    ) -> Result<(), String> {
+        let mut baseline = Self::load_existing(dir, name)?.unwrap_or_default();
+        if baseline.fingerprint.is_some() {
+            baseline.check_fingerprint(name, fingerprint)?;
+        }
+        baseline.fingerprint = Some(fingerprint.clone());
+        baseline
+            .runs
+            .retain(|run| !results.iter().any(|result| result.same_run(run)));
+        baseline.runs.extend_from_slice(results);
+
+        let path = Self::path(dir, name)?;
+        std::fs::create_dir_all(dir)
+            .and_then(|_| File::create(&path))
+            .map_err(|err| err.to_string())
+            .and_then(|file| {
+                serde_json::to_writer_pretty(BufWriter::new(file), &baseline)
+                    .map_err(|err| err.to_string())
+            })
+            .map_err(|err| format!("Cannot save baseline {}: {}", path.display(), err))
+    }
+
+    /// Fails unless the baseline was recorded on a system matching `fingerprint`.
+    pub fn check_fingerprint(&self, name: &str, fingerprint: &Fingerprint) -> Result<(), String> {
+        let recorded = self.fingerprint.as_ref().ok_or_else(|| {
+            format!(
+                "Baseline {} has no system fingerprint, save it again to compare against it",
+                name
+            )
+        })?;
+        let differences = recorded.differences(fingerprint);
+        if differences.is_empty() {
+            return Ok(());
+        }
+        Err(format!(
+            "Baseline {} was recorded on a different system, differing in {}",
+            name,
+            differences.join(", ")
+        ))
+    }
+
+    fn find(&self, result: &RunResult) -> Option<&RunResult> {
+        self.runs.iter().find(|run| run.same_run(result))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Regressed,
+    Improved,
+    Unchanged,
+    NoBaseline,
+}
+
+impl Verdict {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Verdict::Regressed => "REGRESSED",
+            Verdict::Improved => "improved",
+            Verdict::Unchanged => "unchanged",
+            Verdict::NoBaseline => "no baseline",
+        }
+    }
+}
+
+/// One line of the baseline comparison table.
+pub struct Comparison {
+    pub benchmark: String,
+    pub threads: usize,
+    pub unit: Unit,
+    pub baseline: Option<f64>,
+    pub current: f64,
+    /// Relative change of the mean throughput
+    pub change: Option<f64>,
+    pub p_value: Option<f64>,
+    pub verdict: Verdict,
+}
+
+/// Compares each result with the same run of the baseline: a significant change
+/// of the mean beyond `threshold` (a fraction) is a regression or an improvement.
+pub fn compare(baseline: &Baseline, results: &[RunResult], threshold: f64) -> Vec<Comparison> {
+    results
+        .iter()
+        .map(|result| {
+            let current = result.mean();
+            let base = baseline.find(result);
+            let (change, p_value) = match base {
+                Some(base) => (
+                    Some((current - base.mean()) / base.mean()),
+                    Some(mann_whitney_p_value(&base.ops_per_sec, &result.ops_per_sec)),
+                ),
+                None => (None, None),
+            };
+            let verdict = match (change, p_value) {
+                (Some(change), Some(p)) if p < SIGNIFICANCE && change < -threshold => {
+                    Verdict::Regressed
+                }
+                (Some(change), Some(p)) if p < SIGNIFICANCE && change > threshold => {
+                    Verdict::Improved
+                }
+                (Some(_), _) => Verdict::Unchanged,
+                (None, _) => Verdict::NoBaseline,
+            };
+            Comparison {
+                benchmark: result.benchmark.clone(),
+                threads: result.threads,
+                unit: result.unit,
+                baseline: base.map(RunResult::mean),
+                current,
+                change,
+                p_value,
+                verdict,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::baseline::{compare, Baseline, RunResult, Verdict};
+    use crate::benchmarks::Unit;
+
+    fn result(benchmark: &str, ops_per_sec: Vec<f64>) -> RunResult {
+        RunResult {
+            benchmark: benchmark.to_string(),
+            threads: 4,
+            options: serde_json::json!({ "stride": 64 }),
+            unit: Unit::Ops,
+            ops_per_sec,
+        }
+    }
+
+    #[test]
+    fn verdicts() {
+        let base: Vec<f64> = (0..10).map(|i| 100.0 + i as f64).collect();
+        let baseline = Baseline {
+            fingerprint: None,
+            runs: vec![result("a", base.clone()), result("b", base.clone())],
+        };
+        let results = [
+            result("a", base.iter().map(|x| x * 0.8).collect()),
+            result("b", base.iter().map(|x| x + 1.0).collect()),
+            result("c", base.clone()),
+        ];
+        let comparisons = compare(&baseline, &results, 0.05);
+        let verdicts: Vec<_> = comparisons.iter().map(|c| c.verdict).collect();
+        assert_eq!(
+            verdicts,
+            [Verdict::Regressed, Verdict::Unchanged, Verdict::NoBaseline]
+        );
+    }
+
+    #[test]
+    fn names() {
+        let dir = std::env::temp_dir().join("rust-test-no-baselines");
+        assert!(Baseline::load_existing(&dir, "main").unwrap().is_none());
+        for name in ["", "../main", "a/b", "..", "a\\b"] {
+            assert!(Baseline::load_existing(&dir, name).is_err());
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use crate::benchmarks::strided::StridedSlots;
+use crate::benchmarks::{BenchContext, Registry};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Memory ordering of an atomic benchmark, as a type so that each variant is monomorphized.
+trait MemOrder: 'static {
+    const NAME: &'static str;
+    const LOAD: Ordering;
+    const RMW: Ordering;
+    const CAS_FAILURE: Ordering;
+}
+
+struct Relaxed;
+struct AcqRel;
+struct SeqCst;
+
+impl MemOrder for Relaxed {
+    const NAME: &'static str = "relaxed";
+    const LOAD: Ordering = Ordering::Relaxed;
+    const RMW: Ordering = Ordering::Relaxed;
+    const CAS_FAILURE: Ordering = Ordering::Relaxed;
+}
+
+impl MemOrder for AcqRel {
+    const NAME: &'static str = "acqrel";
+    const LOAD: Ordering = Ordering::Acquire;
+    const RMW: Ordering = Ordering::AcqRel;
+    const CAS_FAILURE: Ordering = Ordering::Acquire;
+}
+
+impl MemOrder for SeqCst {
+    const NAME: &'static str = "seqcst";
+    const LOAD: Ordering = Ordering::SeqCst;
+    const RMW: Ordering = Ordering::SeqCst;
+    const CAS_FAILURE: Ordering = Ordering::SeqCst;
+}
+
+pub fn register(registry: &mut Registry) -> Result<(), String> {
+    register_ordering::<Relaxed>(registry)?;
+    register_ordering::<AcqRel>(registry)?;
+    register_ordering::<SeqCst>(registry)?;
+    Ok(())
+}
+
+fn register_ordering<O: MemOrder>(registry: &mut Registry) -> Result<(), String> {
+    registry.register_fn(
+        format!("atomic/fetch-add/{}", O::NAME),
+        format!(
+            "All threads fetch_add on a single shared atomic, {}",
+            O::NAME
+        ),
+        test_atomic_inc::<O>,
+    )?;
+    registry.register_fn(
+        format!("atomic/cas-loop/{}", O::NAME),
+        format!(
+            "All threads increment a single shared atomic with a compare_exchange loop, {}",
+            O::NAME
+        ),
+        test_atomic_cas_loop::<O>,
+    )?;
+    registry.register_fn(
+        format!("atomic/swap/{}", O::NAME),
+        format!("All threads swap a single shared atomic, {}", O::NAME),
+        test_atomic_swap::<O>,
+    )?;
+    registry.register_fn(
+        format!("atomic/fetch-max/{}", O::NAME),
+        format!(
+            "All threads fetch_max an increasing value on a single shared atomic, {}",
+            O::NAME
+        ),
+        test_atomic_fetch_max::<O>,
+    )?;
+    registry.register_fn(
+        format!("atomic/uncontended/{}", O::NAME),
+        format!(
+            "Each thread fetch_adds its own atomic, separately allocated, {}",
+            O::NAME
+        ),
+        test_uncontended_atomic::<O>,
+    )?;
+    registry.register_fn(
+        format!("atomic/strided/{}", O::NAME),
+        format!(
+            "Each thread fetch_adds its own atomic, --stride bytes apart in one allocation, {}",
+            O::NAME
+        ),
+        test_atomic_strided::<O>,
+    )?;
+    Ok(())
+}
+
+/// Runs `op` in a loop on every worker against the same atomic, passing a thread-local state.
+fn spawn_on_shared_atomic(
+    ctx: &mut BenchContext,
+    op: impl Fn(&AtomicU64, &mut u64) + Copy + Send + 'static,
+) {
+    let atomic_val = Arc::new(AtomicU64::new(0));
+
+    for index in 0..ctx.threads {
+        let atomic_val = atomic_val.clone();
+        ctx.spawn(move |worker| {
+            let mut local = index as u64;
+            worker.spin(|| op(&atomic_val, &mut local));
+        });
+    }
+}
+
+fn test_atomic_inc<O: MemOrder>(ctx: &mut BenchContext) -> Result<(), String> {
+    spawn_on_shared_atomic(ctx, |atomic_val, _| {
+        atomic_val.fetch_add(1, O::RMW);
+    });
+    Ok(())
+}
+
+fn test_atomic_cas_loop<O: MemOrder>(ctx: &mut BenchContext) -> Result<(), String> {
+    spawn_on_shared_atomic(ctx, |atomic_val, _| {
+        let mut current = atomic_val.load(O::LOAD);
+        while let Err(actual) =
+            atomic_val.compare_exchange(current, current + 1, O::RMW, O::CAS_FAILURE)
+        {
+            current = actual;
+        }
+    });
+    Ok(())
+}
+
+fn test_atomic_swap<O: MemOrder>(ctx: &mut BenchContext) -> Result<(), String> {
+    spawn_on_shared_atomic(ctx, |atomic_val, index| {
+        atomic_val.swap(*index, O::RMW);
+    });
+    Ok(())
+}
+
+fn test_atomic_fetch_max<O: MemOrder>(ctx: &mut BenchContext) -> Result<(), String> {
+    spawn_on_shared_atomic(ctx, |atomic_val, value| {
+        *value += 1;
+        atomic_val.fetch_max(*value, O::RMW);
+    });
+    Ok(())
+}
+
+fn test_uncontended_atomic<O: MemOrder>(ctx: &mut BenchContext) -> Result<(), String> {
+    let mut atomic_vals = vec![];
+
+    for _ in 0..ctx.threads {
+        atomic_vals.push(Arc::new(AtomicU64::new(0)));
+    }
+
+    for atomic_val in &atomic_vals {
+        let atomic_val = atomic_val.clone();
+        ctx.spawn(move |worker| {
+            worker.spin(|| {
+                atomic_val.fetch_add(1, O::RMW);
+            })
+        });
+    }
+    Ok(())
+}
+
+fn test_atomic_strided<O: MemOrder>(ctx: &mut BenchContext) -> Result<(), String> {
+    let atomic_vals = Arc::new(StridedSlots::<AtomicU64>::new(
+        ctx.threads,
+        ctx.options.stride,
+    )?);
+
+    for i in 0..ctx.threads {
+        let atomic_vals = atomic_vals.clone();
+        ctx.spawn(move |worker| {
+            let atomic_val = atomic_vals.get(i);
+            worker.spin(|| {
+                atomic_val.fetch_add(1, O::RMW);
+            })
+        });
+    }
+    Ok(())
+}
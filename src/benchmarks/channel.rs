@@ -0,0 +1,379 @@
+use crate::benchmarks::{BenchContext, Registry, Worker};
+use crossbeam::channel as cb;
+use crossbeam::queue::{ArrayQueue, SegQueue};
+use parking_lot::Mutex;
+use std::hint::black_box;
+use std::sync::{mpsc, Arc};
+use std::time::Instant;
+
+/// A channel or queue under test. The channels block, the queues spin and give up once the run is stopped.
+trait BenchChannel: 'static {
+    const NAME: &'static str;
+    const DESCRIPTION: &'static str;
+    /// Whether `--capacity` bounds the channel, otherwise it limits the backlog of the producers
+    const BOUNDED: bool;
+
+    type Sender<T: Send + 'static>: Clone + Send + 'static;
+    type Receiver<T: Send + 'static>: Clone + Send + Sync + 'static;
+
+    fn channel<T: Send + 'static>(capacity: usize) -> (Self::Sender<T>, Self::Receiver<T>);
+
+    /// Returns false if the message could not be sent: all the receivers are gone or the run is stopped.
+    fn send<T: Send + 'static>(sender: &Self::Sender<T>, msg: T, worker: &Worker) -> bool;
+
+    /// Returns `None` once all the senders are gone, or the run is stopped for the queues.
+    fn recv<T: Send + 'static>(receiver: &Self::Receiver<T>, worker: &Worker) -> Option<T>;
+
+    /// Messages waiting in the channel, if the implementation exposes it.
+    fn len<T: Send + 'static>(_receiver: &Self::Receiver<T>) -> Option<usize> {
+        None
+    }
+}
+
+struct CrossbeamBounded;
+struct CrossbeamUnbounded;
+struct StdSync;
+struct StdUnbounded;
+struct CrossbeamArrayQueue;
+struct CrossbeamSegQueue;
+
+impl BenchChannel for CrossbeamBounded {
+    const NAME: &'static str = "crossbeam-bounded";
+    const DESCRIPTION: &'static str = "crossbeam::channel::bounded";
+    const BOUNDED: bool = true;
+
+    type Sender<T: Send + 'static> = cb::Sender<T>;
+    type Receiver<T: Send + 'static> = cb::Receiver<T>;
+
+    fn channel<T: Send + 'static>(capacity: usize) -> (Self::Sender<T>, Self::Receiver<T>) {
+        cb::bounded(capacity)
+    }
+
+    #[inline(always)]
+    fn send<T: Send + 'static>(sender: &Self::Sender<T>, msg: T, _: &Worker) -> bool {
+        sender.send(msg).is_ok()
+    }
+
+    #[inline(always)]
+    fn recv<T: Send + 'static>(receiver: &Self::Receiver<T>, _: &Worker) -> Option<T> {
+        receiver.recv().ok()
+    }
+
+    fn len<T: Send + 'static>(receiver: &Self::Receiver<T>) -> Option<usize> {
+        Some(receiver.len())
+    }
+}
+
+impl BenchChannel for CrossbeamUnbounded {
+    const NAME: &'static str = "crossbeam-unbounded";
+    const DESCRIPTION: &'static str = "crossbeam::channel::unbounded";
+    const BOUNDED: bool = false;
+
+    type Sender<T: Send + 'static> = cb::Sender<T>;
+    type Receiver<T: Send + 'static> = cb::Receiver<T>;
+
+    fn channel<T: Send + 'static>(_: usize) -> (Self::Sender<T>, Self::Receiver<T>) {
+        cb::unbounded()
+    }
+
+    #[inline(always)]
+    fn send<T: Send + 'static>(sender: &Self::Sender<T>, msg: T, _: &Worker) -> bool {
+        sender.send(msg).is_ok()
+    }
+
+    #[inline(always)]
+    fn recv<T: Send + 'static>(receiver: &Self::Receiver<T>, _: &Worker) -> Option<T> {
+        receiver.recv().ok()
+    }
+
+    fn len<T: Send + 'static>(receiver: &Self::Receiver<T>) -> Option<usize> {
+        Some(receiver.len())
+    }
+}
+
+/// The std receivers are single-consumer, so several consumers share one behind a mutex.
+type SharedReceiver<T> = Arc<Mutex<mpsc::Receiver<T>>>;
+
+impl BenchChannel for StdSync {
+    const NAME: &'static str = "std-sync";
+    const DESCRIPTION: &'static str = "std::sync::mpsc::sync_channel";
+    const BOUNDED: bool = true;
+
+    type Sender<T: Send + 'static> = mpsc::SyncSender<T>;
+    type Receiver<T: Send + 'static> = SharedReceiver<T>;
+
+    fn channel<T: Send + 'static>(capacity: usize) -> (Self::Sender<T>, Self::Receiver<T>) {
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+        (sender, Arc::new(Mutex::new(receiver)))
+    }
+
+    #[inline(always)]
+    fn send<T: Send + 'static>(sender: &Self::Sender<T>, msg: T, _: &Worker) -> bool {
+        sender.send(msg).is_ok()
+    }
+
+    #[inline(always)]
+    fn recv<T: Send + 'static>(receiver: &Self::Receiver<T>, _: &Worker) -> Option<T> {
+        receiver.lock().recv().ok()
+    }
+}
+
+impl BenchChannel for StdUnbounded {
+    const NAME: &'static str = "std-unbounded";
+    const DESCRIPTION: &'static str = "std::sync::mpsc::channel";
+    const BOUNDED: bool = false;
+
+    type Sender<T: Send + 'static> = mpsc::Sender<T>;
+    type Receiver<T: Send + 'static> = SharedReceiver<T>;
+
+    fn channel<T: Send + 'static>(_: usize) -> (Self::Sender<T>, Self::Receiver<T>) {
+        let (sender, receiver) = mpsc::channel();
+        (sender, Arc::new(Mutex::new(receiver)))
+    }
+
+    #[inline(always)]
+    fn send<T: Send + 'static>(sender: &Self::Sender<T>, msg: T, _: &Worker) -> bool {
+        sender.send(msg).is_ok()
+    }
+
+    #[inline(always)]
+    fn recv<T: Send + 'static>(receiver: &Self::Receiver<T>, _: &Worker) -> Option<T> {
+        receiver.lock().recv().ok()
+    }
+}
+
+impl BenchChannel for CrossbeamArrayQueue {
+    const NAME: &'static str = "array-queue";
+    const DESCRIPTION: &'static str = "crossbeam::queue::ArrayQueue";
+    const BOUNDED: bool = true;
+
+    type Sender<T: Send + 'static> = Arc<ArrayQueue<T>>;
+    type Receiver<T: Send + 'static> = Arc<ArrayQueue<T>>;
+
+    fn channel<T: Send + 'static>(capacity: usize) -> (Self::Sender<T>, Self::Receiver<T>) {
+        let queue = Arc::new(ArrayQueue::new(capacity.max(1)));
+        (queue.clone(), queue)
+    }
+
+    #[inline(always)]
+    fn send<T: Send + 'static>(sender: &Self::Sender<T>, mut msg: T, worker: &Worker) -> bool {
+        loop {
+            match sender.push(msg) {
+                Ok(()) => return true,
+                Err(_) if worker.is_stopped() => return false,
+                Err(rejected) => msg = rejected,
+            }
+            std::hint::spin_loop();
+        }
+    }
+
+    #[inline(always)]
+    fn recv<T: Send + 'static>(receiver: &Self::Receiver<T>, worker: &Worker) -> Option<T> {
+        spin_pop(|| receiver.pop(), worker)
+    }
+
+    fn len<T: Send + 'static>(receiver: &Self::Receiver<T>) -> Option<usize> {
+        Some(receiver.len())
+    }
+}
+
+impl BenchChannel for CrossbeamSegQueue {
+    const NAME: &'static str = "seg-queue";
+    const DESCRIPTION: &'static str = "crossbeam::queue::SegQueue";
+    const BOUNDED: bool = false;
+
+    type Sender<T: Send + 'static> = Arc<SegQueue<T>>;
+    type Receiver<T: Send + 'static> = Arc<SegQueue<T>>;
+
+    fn channel<T: Send + 'static>(_: usize) -> (Self::Sender<T>, Self::Receiver<T>) {
+        let queue = Arc::new(SegQueue::new());
+        (queue.clone(), queue)
+    }
+
+    #[inline(always)]
+    fn send<T: Send + 'static>(sender: &Self::Sender<T>, msg: T, _: &Worker) -> bool {
+        sender.push(msg);
+        true
+    }
+
+    #[inline(always)]
+    fn recv<T: Send + 'static>(receiver: &Self::Receiver<T>, worker: &Worker) -> Option<T> {
+        spin_pop(|| receiver.pop(), worker)
+    }
+
+    fn len<T: Send + 'static>(receiver: &Self::Receiver<T>) -> Option<usize> {
+        Some(receiver.len())
+    }
+}
+
+#[inline(always)]
+fn spin_pop<T>(pop: impl Fn() -> Option<T>, worker: &Worker) -> Option<T> {
+    loop {
+        if let Some(msg) = pop() {
+            return Some(msg);
+        }
+        if worker.is_stopped() {
+            return None;
+        }
+        std::hint::spin_loop();
+    }
+}
+
+/// Message carrying `N` bytes of payload, copied through the channel by value.
+#[derive(Clone, Copy)]
+struct Message<const N: usize>([u8; N]);
+
+/// Calls `$run::<$channel, N>($ctx)` with `N` the selected `--message-size`.
+macro_rules! with_message_size {
+    ($run:ident::<$channel:ty>($ctx:expr)) => {
+        match $ctx.options.message_size {
+            8 => $run::<$channel, 8>($ctx),
+            16 => $run::<$channel, 16>($ctx),
+            32 => $run::<$channel, 32>($ctx),
+            64 => $run::<$channel, 64>($ctx),
+            128 => $run::<$channel, 128>($ctx),
+            256 => $run::<$channel, 256>($ctx),
+            512 => $run::<$channel, 512>($ctx),
+            1024 => $run::<$channel, 1024>($ctx),
+            2048 => $run::<$channel, 2048>($ctx),
+            4096 => $run::<$channel, 4096>($ctx),
+            size => Err(format!("Unsupported message size {}", size)),
+        }
+    };
+}
+
+pub fn register(registry: &mut Registry) -> Result<(), String> {
+    register_channel::<CrossbeamBounded>(registry)?;
+    register_channel::<CrossbeamUnbounded>(registry)?;
+    register_channel::<StdSync>(registry)?;
+    register_channel::<StdUnbounded>(registry)?;
+    register_channel::<CrossbeamArrayQueue>(registry)?;
+    register_channel::<CrossbeamSegQueue>(registry)?;
+    Ok(())
+}
+
+fn register_channel<C: BenchChannel>(registry: &mut Registry) -> Result<(), String> {
+    registry.register_fn(
+        format!("channel/{}", C::NAME),
+        format!(
+            "Producers send messages to consumers through a shared {} (see --producers, --consumers, \
+             --capacity and --message-size)",
+            C::DESCRIPTION
+        ),
+        test_throughput::<C>,
+    )?;
+    registry.register_fn(
+        format!("channel/{}/round-trip", C::NAME),
+        format!(
+            "Pairs of threads bounce a message over two {}, recording the round-trip latency",
+            C::DESCRIPTION
+        ),
+        test_round_trip::<C>,
+    )?;
+    Ok(())
+}
+
+fn test_throughput<C: BenchChannel>(ctx: &mut BenchContext) -> Result<(), String> {
+    with_message_size!(spawn_throughput::<C>(ctx))
+}
+
+fn test_round_trip<C: BenchChannel>(ctx: &mut BenchContext) -> Result<(), String> {
+    with_message_size!(spawn_round_trip::<C>(ctx))
+}
+
+/// Consumers count one operation per received message. Producers of the unbounded channels
+/// wait while `--capacity` messages are in flight, so that the backlog cannot exhaust the memory.
+fn spawn_throughput<C: BenchChannel, const N: usize>(ctx: &mut BenchContext) -> Result<(), String> {
+    let producers = ctx.options.producers.unwrap_or((ctx.threads / 2).max(1));
+    let consumers = ctx
+        .options
+        .consumers
+        .unwrap_or(ctx.threads.saturating_sub(producers).max(1));
+    let backlog = match C::BOUNDED {
+        true => None,
+        false => Some((
+            ctx.metrics().gauge("in-flight")?,
+            ctx.options.capacity.max(1) as i64,
+        )),
+    };
+
+    let (sender, receiver) = C::channel::<Message<N>>(ctx.options.capacity);
+    let receiver = Arc::new(receiver);
+    if C::len(&receiver).is_some() {
+        // Weak, so that the channel disconnects once the consumers are gone
+        let receiver = Arc::downgrade(&receiver);
+        ctx.metrics().gauge_fn("queue-depth", move || {
+            let len = receiver.upgrade().and_then(|receiver| C::len(&receiver));
+            len.unwrap_or_default() as f64
+        })?;
+    }
+
+    for _ in 0..consumers {
+        let receiver = receiver.clone();
+        let backlog = backlog.clone();
+        ctx.spawn(move |worker| {
+            while let Some(msg) = C::recv(&receiver, &worker) {
+                black_box(msg);
+                if let Some((in_flight, _)) = &backlog {
+                    in_flight.add(-1);
+                }
+                worker.count(1);
+            }
+        });
+    }
+
+    for index in 0..producers {
+        let sender = sender.clone();
+        let backlog = backlog.clone();
+        ctx.spawn(move |worker| {
+            let msg = Message([index as u8; N]);
+            while !worker.is_stopped() {
+                if let Some((in_flight, limit)) = &backlog {
+                    if in_flight.get() >= *limit {
+                        std::hint::spin_loop();
+                        continue;
+                    }
+                    in_flight.add(1);
+                }
+                if !C::send(&sender, black_box(msg), &worker) {
+                    break;
+                }
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Both sides of a pair count the messages they receive, the client records the round-trip latency.
+fn spawn_round_trip<C: BenchChannel, const N: usize>(ctx: &mut BenchContext) -> Result<(), String> {
+    for index in 0..(ctx.threads / 2).max(1) {
+        let (ping_sender, ping_receiver) = C::channel::<Message<N>>(ctx.options.capacity);
+        let (pong_sender, pong_receiver) = C::channel::<Message<N>>(ctx.options.capacity);
+
+        ctx.spawn(move |worker| {
+            while let Some(msg) = C::recv(&ping_receiver, &worker) {
+                worker.count(1);
+                if !C::send(&pong_sender, msg, &worker) {
+                    break;
+                }
+            }
+        });
+
+        ctx.spawn(move |worker| {
+            let msg = Message([index as u8; N]);
+            while !worker.is_stopped() {
+                let start = Instant::now();
+                if !C::send(&ping_sender, black_box(msg), &worker) {
+                    break;
+                }
+                match C::recv(&pong_receiver, &worker) {
+                    Some(reply) => black_box(reply),
+                    None => break,
+                };
+                worker.record_latency(start.elapsed());
+                worker.count(1);
+            }
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,152 @@
+use crate::benchmarks::{BenchContext, Registry, XorShift64};
+use std::hint::black_box;
+use std::time::Instant;
+
+/// Loads timed together, amortizing the cost of reading the clock.
+const BATCH_LOADS: u64 = 1024;
+
+/// Each link of the chase sits alone in its cache line.
+const LINE_WORDS: usize = 64 / std::mem::size_of::<usize>();
+
+/// Size and alignment of a transparent huge page on x86_64 and most aarch64 kernels.
+const HUGE_PAGE_SIZE: usize = 2 << 20;
+
+pub fn register(registry: &mut Registry) -> Result<(), String> {
+    registry.register_fn(
+        "memory/chase",
+        "Each thread follows a random cycle through its own --working-set bytes, \
+         one dependent load per cache line (see --huge-pages)",
+        test_pointer_chase,
+    )?;
+    Ok(())
+}
+
+/// Anonymous mapping holding a random cyclic chase, where the first word of each
+/// cache line is the index of the next line to load.
+struct ChaseBuffer {
+    map: *mut libc::c_void,
+    map_len: usize,
+    lines: *mut usize,
+    count: usize,
+}
+
+impl ChaseBuffer {
+    /// Maps the buffer, huge page aligned, and advises the kernel for or against huge pages
+    /// before its pages are faulted in, so that the setting of the system does not matter.
+    fn new(size: usize, huge_pages: bool, seed: u64) -> Result<Self, String> {
+        let map_len = size + HUGE_PAGE_SIZE;
+        let map = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                map_len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if map == libc::MAP_FAILED {
+            return Err(format!(
+                "Cannot map the chase buffer: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+        let start = (map as usize).next_multiple_of(HUGE_PAGE_SIZE);
+        let advice = match huge_pages {
+            true => libc::MADV_HUGEPAGE,
+            false => libc::MADV_NOHUGEPAGE,
+        };
+        let mut buffer = Self {
+            map,
+            map_len,
+            lines: start as *mut usize,
+            count: size / 64,
+        };
+        // Without transparent huge pages in the kernel, the buffer is on small pages anyway
+        if unsafe { libc::madvise(start as *mut libc::c_void, size, advice) } != 0 && huge_pages {
+            return Err(format!(
+                "Cannot advise huge pages for the chase buffer: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+        buffer.shuffle(seed);
+        Ok(buffer)
+    }
+
+    #[inline(always)]
+    fn next(&self, line: usize) -> usize {
+        unsafe { *self.lines.add(line * LINE_WORDS) }
+    }
+
+    fn set_next(&mut self, line: usize, next: usize) {
+        unsafe { *self.lines.add(line * LINE_WORDS) = next }
+    }
+
+    /// Links all the lines in a single random cycle with Sattolo's algorithm,
+    /// defeating the prefetchers while making every line part of the working set.
+    fn shuffle(&mut self, seed: u64) {
+        for line in 0..self.count {
+            self.set_next(line, line);
+        }
+        let mut rng = XorShift64(seed);
+        for line in (1..self.count).rev() {
+            let other = (rng.next_u64() % line as u64) as usize;
+            let (a, b) = (self.next(line), self.next(other));
+            self.set_next(line, b);
+            self.set_next(other, a);
+        }
+    }
+}
+
+impl Drop for ChaseBuffer {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.map, self.map_len) };
+    }
+}
+
+unsafe impl Send for ChaseBuffer {}
+
+fn test_pointer_chase(ctx: &mut BenchContext) -> Result<(), String> {
+    let size = ctx.options.working_set as usize;
+    let huge_pages = ctx.options.huge_pages;
+
+    for i in 0..ctx.threads {
+        ctx.spawn_after_setup(
+            // Built by the worker, so that the buffer is local to its cpu
+            move || ChaseBuffer::new(size, huge_pages, 0x9e3779b97f4a7c15 ^ i as u64),
+            |worker, buffer| {
+                let mut line = 0;
+                while !worker.is_stopped() {
+                    let start = Instant::now();
+                    for _ in 0..BATCH_LOADS {
+                        line = buffer.next(line);
+                    }
+                    worker.record_latency(start.elapsed() / BATCH_LOADS as u32);
+                    worker.count(BATCH_LOADS);
+                }
+                black_box(line);
+            },
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::benchmarks::chase::ChaseBuffer;
+
+    #[test]
+    fn single_cycle() {
+        for huge_pages in [false, true] {
+            let buffer = ChaseBuffer::new(64 << 10, huge_pages, 42).unwrap();
+            let mut visited = vec![false; buffer.count];
+            let mut line = 0;
+            for _ in 0..buffer.count {
+                assert!(!visited[line]);
+                visited[line] = true;
+                line = buffer.next(line);
+            }
+            assert_eq!(line, 0);
+        }
+    }
+}
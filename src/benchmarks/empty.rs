@@ -0,0 +1,17 @@
+use crate::benchmarks::{BenchContext, Registry};
+
+pub fn register(registry: &mut Registry) -> Result<(), String> {
+    registry.register_fn(
+        "empty",
+        "Empty spinning loop on every thread, no shared state",
+        test_empty,
+    )?;
+    Ok(())
+}
+
+fn test_empty(ctx: &mut BenchContext) -> Result<(), String> {
+    for _ in 0..ctx.threads {
+        ctx.spawn(move |worker| worker.spin(|| {}));
+    }
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use crate::benchmarks::strided::StridedSlots;
+use crate::benchmarks::{BenchContext, Registry};
+use std::cell::UnsafeCell;
+use std::sync::Arc;
+
+pub fn register(registry: &mut Registry) -> Result<(), String> {
+    registry.register_fn(
+        "integer/strided",
+        "Each thread increments its own volatile integer, --stride bytes apart in one allocation",
+        test_integer_strided,
+    )?;
+    Ok(())
+}
+
+/// Plain integer incremented through volatile accesses by a single thread.
+struct VolatileInt(UnsafeCell<u64>);
+unsafe impl Sync for VolatileInt {}
+
+impl VolatileInt {
+    #[inline(always)]
+    fn increment(&self) {
+        unsafe {
+            let val = std::ptr::read_volatile(self.0.get()) + 1;
+            std::ptr::write_volatile(self.0.get(), val);
+        }
+    }
+}
+
+fn test_integer_strided(ctx: &mut BenchContext) -> Result<(), String> {
+    let vals = Arc::new(StridedSlots::<VolatileInt>::new(
+        ctx.threads,
+        ctx.options.stride,
+    )?);
+
+    for i in 0..ctx.threads {
+        let vals = vals.clone();
+        ctx.spawn(move |worker| {
+            let val = vals.get(i);
+            worker.spin(|| {
+                val.increment();
+            })
+        });
+    }
+    Ok(())
+}
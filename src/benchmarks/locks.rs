@@ -0,0 +1,136 @@
+//! Hand-written spinning locks, to compare against the std and parking_lot mutexes.
+
+use std::cell::UnsafeCell;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU32, Ordering};
+
+/// Test-and-test-and-set spinlock: waiters spin on a plain load and only
+/// attempt the swap once the lock looks free.
+#[derive(Default)]
+pub struct TtasLock<T> {
+    locked: AtomicBool,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Sync for TtasLock<T> {}
+
+impl<T> TtasLock<T> {
+    #[inline(always)]
+    pub fn with_lock<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        loop {
+            while self.locked.load(Ordering::Relaxed) {
+                std::hint::spin_loop();
+            }
+            if !self.locked.swap(true, Ordering::Acquire) {
+                break;
+            }
+        }
+        let _unlock = OnDrop(|| self.locked.store(false, Ordering::Release));
+        f(unsafe { &mut *self.data.get() })
+    }
+}
+
+/// FIFO spinlock: every waiter takes a ticket and spins until it is served.
+#[derive(Default)]
+pub struct TicketLock<T> {
+    next_ticket: AtomicU32,
+    now_serving: AtomicU32,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Sync for TicketLock<T> {}
+
+impl<T> TicketLock<T> {
+    #[inline(always)]
+    pub fn with_lock<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        let ticket = self.next_ticket.fetch_add(1, Ordering::Relaxed);
+        while self.now_serving.load(Ordering::Acquire) != ticket {
+            std::hint::spin_loop();
+        }
+        let _unlock = OnDrop(|| {
+            self.now_serving
+                .store(ticket.wrapping_add(1), Ordering::Release)
+        });
+        f(unsafe { &mut *self.data.get() })
+    }
+}
+
+/// Queue node of an MCS lock, owned by the waiting thread's stack frame.
+#[repr(align(128))]
+struct McsNode {
+    next: AtomicPtr<McsNode>,
+    locked: AtomicBool,
+}
+
+/// Mellor-Crummey and Scott queue lock: each waiter spins on its own node,
+/// and the lock is handed over directly to the next one in the queue.
+pub struct McsLock<T> {
+    tail: AtomicPtr<McsNode>,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Sync for McsLock<T> {}
+
+impl<T: Default> Default for McsLock<T> {
+    fn default() -> Self {
+        Self {
+            tail: AtomicPtr::new(null_mut()),
+            data: Default::default(),
+        }
+    }
+}
+
+impl<T> McsLock<T> {
+    #[inline(always)]
+    pub fn with_lock<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        let node = McsNode {
+            next: AtomicPtr::new(null_mut()),
+            locked: AtomicBool::new(true),
+        };
+        let node_ptr = &node as *const McsNode as *mut McsNode;
+
+        let prev = self.tail.swap(node_ptr, Ordering::AcqRel);
+        if !prev.is_null() {
+            // The predecessor is still in its critical section, so its node is alive
+            unsafe { (*prev).next.store(node_ptr, Ordering::Release) };
+            while node.locked.load(Ordering::Acquire) {
+                std::hint::spin_loop();
+            }
+        }
+
+        let _unlock = OnDrop(|| self.unlock(&node));
+        f(unsafe { &mut *self.data.get() })
+    }
+
+    fn unlock(&self, node: &McsNode) {
+        let mut next = node.next.load(Ordering::Acquire);
+        if next.is_null() {
+            let node_ptr = node as *const McsNode as *mut McsNode;
+            if self
+                .tail
+                .compare_exchange(node_ptr, null_mut(), Ordering::Release, Ordering::Relaxed)
+                .is_ok()
+            {
+                return;
+            }
+            // A successor swapped the tail but has not linked itself yet
+            loop {
+                next = node.next.load(Ordering::Acquire);
+                if !next.is_null() {
+                    break;
+                }
+                std::hint::spin_loop();
+            }
+        }
+        unsafe { (*next).locked.store(false, Ordering::Release) };
+    }
+}
+
+/// Runs the closure when dropped, so that the locks are released even if the critical section panics.
+struct OnDrop<F: FnMut()>(F);
+
+impl<F: FnMut()> Drop for OnDrop<F> {
+    fn drop(&mut self) {
+        (self.0)()
+    }
+}
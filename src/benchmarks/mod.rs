@@ -0,0 +1,610 @@
+pub mod atomic;
+pub mod channel;
+pub mod chase;
+pub mod empty;
+pub mod integer;
+mod locks;
+pub mod mutex;
+pub mod pingpong;
+pub mod rwlock;
+pub mod stream;
+mod strided;
+pub mod work;
+pub mod writing_test;
+
+use crate::affinity::pin_current_thread;
+use crate::benchmarks::work::Work;
+use crate::benchmarks::writing_test::FileMode;
+use crate::counters::PaddedCounter;
+use crate::harness::{thread_panicked, RunShared};
+use crate::latency::LatencyRecorder;
+use crate::metrics::Metrics;
+use crate::stop_signal::StopSignal;
+use crate::thread_stats::current_tid;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use structopt::StructOpt;
+
+/// A named workload that spawns its worker threads through a `BenchContext`.
+pub trait Benchmark: Sync {
+    fn name(&self) -> &str;
+    fn description(&self) -> &str;
+    /// Spawns the workers of a run, failing the run on an error.
+    fn run(&self, ctx: &mut BenchContext) -> Result<(), String>;
+
+    /// What the workers count with `Worker::count`.
+    fn unit(&self) -> Unit {
+        Unit::Ops
+    }
+
+    /// Whether the counted bytes measure the memory bandwidth, also reported in GB/s.
+    fn is_memory_bandwidth(&self) -> bool {
+        false
+    }
+}
+
+/// Unit of the counted throughput.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Unit {
+    Ops,
+    Bytes,
+}
+
+impl Unit {
+    /// Label of the rates, which are always reported divided by 2^20.
+    pub fn rate_label(&self) -> &'static str {
+        match self {
+            Unit::Ops => "M/s",
+            Unit::Bytes => "MiB/s",
+        }
+    }
+}
+
+/// Parameters of the individual benchmarks, each one ignored by the benchmarks that do not use it.
+#[derive(StructOpt, Serialize, Clone, Debug)]
+pub struct BenchOptions {
+    /// Percentage of write acquisitions in the RwLock benchmarks
+    #[structopt(long, default_value = "10", parse(try_from_str = parse_percent))]
+    pub write_percent: u32,
+    /// Busy work inside each lock acquisition, in spin iterations or as a time (`200ns`, `1us`)
+    #[structopt(long, default_value = "0")]
+    pub critical_work: Work,
+    /// Busy work between two lock acquisitions, in spin iterations or as a time
+    #[structopt(long, default_value = "0")]
+    pub think_work: Work,
+    /// Distance in bytes between the per-thread values of the strided benchmarks
+    #[structopt(long, default_value = "4096", parse(try_from_str = parse_stride))]
+    pub stride: usize,
+    /// Producer threads of the channel benchmarks, by default half of the threads
+    #[structopt(long, parse(try_from_str = parse_nonzero))]
+    pub producers: Option<usize>,
+    /// Consumer threads of the channel benchmarks, by default the remaining threads
+    #[structopt(long, parse(try_from_str = parse_nonzero))]
+    pub consumers: Option<usize>,
+    /// Capacity of the bounded channels, and the maximum backlog of the unbounded ones
+    #[structopt(long, default_value = "1024")]
+    pub capacity: usize,
+    /// Buckets written by the writing benchmarks
+    #[structopt(long, default_value = "256", parse(try_from_str = parse_buckets))]
+    pub buckets: usize,
+    /// Size in bytes of each element written to the buckets
+    #[structopt(long, default_value = "4", parse(try_from_str = parse_nonzero))]
+    pub element_size: usize,
+    /// Per-thread buffer of the bucket dispatcher, in bytes or with a K, M or G suffix
+    #[structopt(long, default_value = "64K", parse(try_from_str = parse_size))]
+    pub dispatcher_buffer: u64,
+    /// Memory reserved by the in-memory file system, in bytes or with a K, M or G suffix
+    #[structopt(long, default_value = "64G", parse(try_from_str = parse_size))]
+    pub fs_memory: u64,
+    /// Storage of the bucket files: `disk-only`, `always-memory` or `prefer-memory[:<swap priority>]`
+    #[structopt(long, default_value = "disk-only")]
+    pub file_mode: FileMode,
+    /// Threads flushing the bucket files to disk
+    #[structopt(long, default_value = "3")]
+    pub flush_threads: usize,
+    /// Directory of the bucket files, by default the temporary directory
+    #[structopt(long)]
+    pub bucket_dir: Option<PathBuf>,
+    /// Payload of the channel messages in bytes, a power of two from 8 to 4096
+    #[structopt(long, default_value = "8", parse(try_from_str = parse_message_size))]
+    pub message_size: usize,
+    /// Size of each array of the stream benchmarks, per thread, in bytes or with a K, M or G suffix
+    #[structopt(long, default_value = "64M", parse(try_from_str = parse_lines_size))]
+    pub buffer_size: u64,
+    /// Write the arrays of the stream benchmarks with non-temporal stores, bypassing the caches
+    #[structopt(long)]
+    pub non_temporal: bool,
+    /// Memory chased by each thread of the pointer-chasing benchmark, in bytes or with a K, M or G suffix.
+    /// Building the chase takes a few seconds past 1G, use a longer --warmup
+    #[structopt(long, default_value = "64M", parse(try_from_str = parse_lines_size))]
+    pub working_set: u64,
+    /// Advise the kernel to back the pointer chase with transparent huge pages
+    #[structopt(long)]
+    pub huge_pages: bool,
+    /// Time one in this many operations of the spinning benchmarks, such as the lock and atomic ones,
+    /// into per-thread latency histograms; the latencies include reading the clock. 0 disables it
+    #[structopt(long, default_value = "0")]
+    pub latency_sample: u64,
+    /// Threads spinning next to the workers without being counted, to oversubscribe the cpus
+    #[structopt(long, default_value = "0")]
+    pub background_spinners: usize,
+    /// Nice value of the background spinners, from -20 (highest priority) to 19
+    #[structopt(long, default_value = "19", parse(try_from_str = parse_nice))]
+    pub background_nice: i32,
+}
+
+/// The defaults of the command line options.
+impl Default for BenchOptions {
+    fn default() -> Self {
+        Self::from_iter(["bench-options"])
+    }
+}
+
+fn parse_percent(s: &str) -> Result<u32, String> {
+    match s.parse() {
+        Ok(percent) if percent <= 100 => Ok(percent),
+        _ => Err(format!("Invalid percentage '{}'", s)),
+    }
+}
+
+/// Largest `--stride`, far past any cache or page effect.
+const MAX_STRIDE: usize = 1 << 30;
+
+pub fn parse_stride(s: &str) -> Result<usize, String> {
+    match s.parse::<usize>() {
+        Ok(stride) if (1..=MAX_STRIDE).contains(&stride) && stride.is_multiple_of(8) => Ok(stride),
+        _ => Err(format!(
+            "Invalid stride '{}', expected a multiple of 8 bytes up to 1G",
+            s
+        )),
+    }
+}
+
+fn parse_buckets(s: &str) -> Result<usize, String> {
+    match s.parse::<usize>() {
+        Ok(buckets) if (1..=1 << 16).contains(&buckets) => Ok(buckets),
+        _ => Err(format!("Invalid bucket count '{}', expected 1 to 65536", s)),
+    }
+}
+
+fn parse_nonzero(s: &str) -> Result<usize, String> {
+    match s.parse::<usize>() {
+        Ok(value) if value > 0 => Ok(value),
+        _ => Err(format!(
+            "Invalid value '{}', expected a positive integer",
+            s
+        )),
+    }
+}
+
+/// Parses a size in bytes, with an optional binary `K`, `M` or `G` suffix.
+fn parse_size(s: &str) -> Result<u64, String> {
+    let units = [("K", 1 << 10), ("M", 1 << 20), ("G", 1 << 30)];
+    let (value, scale) = units
+        .iter()
+        .find_map(|(suffix, scale)| Some((s.strip_suffix(suffix)?, *scale)))
+        .unwrap_or((s, 1));
+    match value.trim().parse::<u64>() {
+        Ok(value) if value > 0 => value
+            .checked_mul(scale)
+            .ok_or_else(|| format!("Size '{}' is too large", s)),
+        _ => Err(format!(
+            "Invalid size '{}', expected bytes or a size like 64K",
+            s
+        )),
+    }
+}
+
+fn parse_message_size(s: &str) -> Result<usize, String> {
+    match s.parse::<usize>() {
+        Ok(size) if size.is_power_of_two() && (8..=4096).contains(&size) => Ok(size),
+        _ => Err(format!(
+            "Invalid message size '{}', expected a power of two from 8 to 4096",
+            s
+        )),
+    }
+}
+
+fn parse_nice(s: &str) -> Result<i32, String> {
+    match s.parse::<i32>() {
+        Ok(nice) if (-20..=19).contains(&nice) => Ok(nice),
+        _ => Err(format!("Invalid nice value '{}', expected -20 to 19", s)),
+    }
+}
+
+/// Parses a size with `parse_size`, which must be a whole number of cache lines.
+pub fn parse_lines_size(s: &str) -> Result<u64, String> {
+    match parse_size(s)? {
+        size if size.is_multiple_of(64) => Ok(size),
+        _ => Err(format!(
+            "Invalid size '{}', expected a multiple of 64 bytes",
+            s
+        )),
+    }
+}
+
+/// Handed to a benchmark to spawn its workers, which must return once the run is stopped.
+pub struct BenchContext {
+    pub threads: usize,
+    pub options: BenchOptions,
+    shared: RunShared,
+    worker_cpus: Option<Vec<usize>>,
+    workers: Vec<JoinHandle<Result<(), String>>>,
+    finish_hooks: Vec<FinishHook>,
+}
+
+type FinishHook = Box<dyn FnOnce() -> Result<Option<Phase>, String>>;
+
+/// Throughput of work done by a finish hook once the workers have exited,
+/// such as reading back what they wrote, reported apart from the sampled run.
+pub struct Phase {
+    pub name: &'static str,
+    pub unit: Unit,
+    pub amount: u64,
+    pub elapsed: Duration,
+}
+
+impl Phase {
+    pub fn per_sec(&self) -> f64 {
+        self.amount as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+impl BenchContext {
+    pub fn new(
+        threads: usize,
+        options: BenchOptions,
+        shared: RunShared,
+        worker_cpus: Option<Vec<usize>>,
+    ) -> Self {
+        Self {
+            threads,
+            options,
+            shared,
+            worker_cpus,
+            workers: vec![],
+            finish_hooks: vec![],
+        }
+    }
+
+    /// Spawns a worker thread, pinned to its cpu if a pinning policy is active.
+    pub fn spawn(&mut self, worker: impl FnOnce(Worker) + Send + 'static) {
+        self.spawn_fallible(move |handle| {
+            worker(handle);
+            Ok(())
+        });
+    }
+
+    /// Spawns a worker able to fail, which stops the run and fails it once joined.
+    fn spawn_fallible(
+        &mut self,
+        worker: impl FnOnce(Worker) -> Result<(), String> + Send + 'static,
+    ) {
+        let handle = Worker {
+            stop: self.shared.stop.clone(),
+            counter: self.shared.counters.register(),
+            latencies: RefCell::new(self.shared.latencies.recorder()),
+            latency_sample: self.options.latency_sample,
+        };
+        let cpu = self
+            .worker_cpus
+            .as_ref()
+            .map(|cpus| cpus[self.workers.len() % cpus.len()]);
+        // The tracker reads the cpu times of the workers from their start
+        let started = self.shared.setup.begin();
+        self.workers.push(std::thread::spawn(move || {
+            handle.counter.set_tid(current_tid());
+            let stop = handle.stop.clone();
+            let pinned = cpu.map(|cpu| {
+                pin_current_thread(cpu)
+                    .map_err(|err| format!("Cannot pin a worker thread to cpu {}: {}", cpu, err))
+            });
+            let result = match pinned {
+                Some(Err(err)) => Err(err),
+                _ => {
+                    drop(started);
+                    worker(handle)
+                }
+            };
+            if result.is_err() {
+                stop.stop();
+            }
+            result
+        }));
+    }
+
+    /// Spawns a worker running `setup` first on its own thread, such as allocating and filling its
+    /// buffers, the warmup and the sampling only starting once the setups of all the workers are done.
+    /// A failed setup stops the run and fails it.
+    pub fn spawn_after_setup<S>(
+        &mut self,
+        setup: impl FnOnce() -> Result<S, String> + Send + 'static,
+        worker: impl FnOnce(Worker, S) + Send + 'static,
+    ) {
+        let pending = self.shared.setup.begin();
+        self.spawn_fallible(move |handle| {
+            let state = setup()?;
+            drop(pending);
+            worker(handle, state);
+            Ok(())
+        });
+    }
+
+    /// Number of workers spawned so far.
+    pub fn spawned(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Named counters and gauges sampled and reported along with the throughput.
+    pub fn metrics(&self) -> &Metrics {
+        &self.shared.metrics
+    }
+
+    /// Registers a function to be called after all the workers have been joined,
+    /// optionally returning the result of an extra phase. An error fails the run.
+    pub fn on_finish(&mut self, hook: impl FnOnce() -> Result<Option<Phase>, String> + 'static) {
+        self.finish_hooks.push(Box::new(hook));
+    }
+
+    /// Waits for all the workers to exit, then runs all the finish hooks, returning the first error.
+    pub fn join(self) -> Result<Vec<Phase>, String> {
+        let mut error = None;
+        for worker in self.workers {
+            if let Err(err) = worker
+                .join()
+                .map_err(|panic| thread_panicked("A worker", panic))
+                .and_then(|result| result)
+            {
+                error = error.or(Some(err));
+            }
+        }
+        let mut phases = vec![];
+        for hook in self.finish_hooks {
+            match hook() {
+                Ok(phase) => phases.extend(phase),
+                Err(err) => error = error.or(Some(err)),
+            }
+        }
+        match error {
+            None => Ok(phases),
+            Some(err) => Err(err),
+        }
+    }
+}
+
+/// Passed to each worker thread to poll the stop signal, count its operations and record latencies.
+pub struct Worker {
+    stop: StopSignal,
+    counter: Arc<PaddedCounter>,
+    latencies: RefCell<LatencyRecorder>,
+    /// Operations of `spin` between two timed ones, 0 if none is timed
+    latency_sample: u64,
+}
+
+impl Worker {
+    #[inline(always)]
+    pub fn is_stopped(&self) -> bool {
+        self.stop.is_stopped()
+    }
+
+    #[inline(always)]
+    pub fn count(&self, ops: u64) {
+        self.counter.add(ops);
+    }
+
+    /// Records the latency of one operation, reported as percentiles at the end of the run.
+    #[inline(always)]
+    pub fn record_latency(&self, latency: Duration) {
+        self.latencies.borrow_mut().record(latency);
+    }
+
+    /// Repeats `op` until the run is stopped, counting one operation per call
+    /// and recording the latency of one in `--latency-sample` calls.
+    #[inline(always)]
+    pub fn spin(&self, op: impl FnMut()) {
+        self.spin_then(op, || {})
+    }
+
+    /// As `spin`, running `then` after each call of `op`, outside of the recorded latencies.
+    #[inline(always)]
+    pub fn spin_then(&self, mut op: impl FnMut(), mut then: impl FnMut()) {
+        if self.latency_sample == 0 {
+            while !self.is_stopped() {
+                op();
+                then();
+                self.count(1);
+            }
+            return;
+        }
+        let mut sampled = OneIn::new(self.latency_sample);
+        while !self.is_stopped() {
+            if sampled.tick() {
+                let start = Instant::now();
+                op();
+                self.record_latency(start.elapsed());
+            } else {
+                op();
+            }
+            then();
+            self.count(1);
+        }
+    }
+}
+
+/// Selects one in `n` calls of `tick`, or none if `n` is 0.
+pub struct OneIn {
+    n: u64,
+    left: u64,
+}
+
+impl OneIn {
+    pub fn new(n: u64) -> Self {
+        Self { n, left: n }
+    }
+
+    #[inline(always)]
+    pub fn tick(&mut self) -> bool {
+        if self.n == 0 {
+            return false;
+        }
+        self.left -= 1;
+        if self.left > 0 {
+            return false;
+        }
+        self.left = self.n;
+        true
+    }
+}
+
+/// Xorshift generator, for the workers to draw random numbers without touching shared state.
+pub struct XorShift64(pub u64);
+
+impl XorShift64 {
+    #[inline(always)]
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+/// A benchmark backed by a plain setup function.
+pub struct FnBenchmark {
+    name: String,
+    description: String,
+    run: fn(&mut BenchContext) -> Result<(), String>,
+}
+
+impl Benchmark for FnBenchmark {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn run(&self, ctx: &mut BenchContext) -> Result<(), String> {
+        (self.run)(ctx)
+    }
+}
+
+#[derive(Default)]
+pub struct Registry {
+    benchmarks: Vec<Box<dyn Benchmark>>,
+}
+
+impl Registry {
+    pub fn register(&mut self, benchmark: impl Benchmark + 'static) -> Result<(), String> {
+        if self.benchmarks.iter().any(|b| b.name() == benchmark.name()) {
+            return Err(format!("Duplicate benchmark name {}", benchmark.name()));
+        }
+        self.benchmarks.push(Box::new(benchmark));
+        Ok(())
+    }
+
+    pub fn register_fn(
+        &mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        run: fn(&mut BenchContext) -> Result<(), String>,
+    ) -> Result<(), String> {
+        self.register(FnBenchmark {
+            name: name.into(),
+            description: description.into(),
+            run,
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Benchmark> {
+        self.benchmarks.iter().map(|b| b.as_ref())
+    }
+
+    /// Returns the benchmarks whose name is equal to `pattern`, or matches it as a glob.
+    pub fn select(&self, pattern: &str) -> Vec<&dyn Benchmark> {
+        if let Some(exact) = self.iter().find(|b| b.name() == pattern) {
+            return vec![exact];
+        }
+        self.iter()
+            .filter(|b| glob_match(pattern.as_bytes(), b.name().as_bytes()))
+            .collect()
+    }
+}
+
+/// Builds the registry with all the benchmarks shipped with this binary.
+pub fn registry() -> Registry {
+    let mut registry = Registry::default();
+    let registered = [
+        empty::register,
+        atomic::register,
+        channel::register,
+        chase::register,
+        integer::register,
+        mutex::register,
+        pingpong::register,
+        rwlock::register,
+        stream::register,
+        writing_test::register,
+    ]
+    .iter()
+    .try_for_each(|register| register(&mut registry));
+    // The built-in benchmarks have distinct names, which the `select` test checks
+    registered.expect("Duplicate built-in benchmark");
+    registry
+}
+
+/// Minimal glob matching, supporting `*` (any sequence) and `?` (any single character).
+fn glob_match(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|skip| glob_match(rest, &name[skip..])),
+        Some((b'?', rest)) => !name.is_empty() && glob_match(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && glob_match(rest, &name[1..]),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::benchmarks::{glob_match, parse_size, registry, OneIn};
+
+    #[test]
+    fn glob() {
+        assert!(glob_match(b"atomic/*", b"atomic/inc"));
+        assert!(glob_match(b"*/inc", b"atomic/inc"));
+        assert!(glob_match(b"mutex/?td", b"mutex/std"));
+        assert!(!glob_match(b"atomic/*", b"mutex/std"));
+        assert!(!glob_match(b"atomic", b"atomic/inc"));
+    }
+
+    #[test]
+    fn one_in() {
+        let mut sampled = OneIn::new(3);
+        let ticks: Vec<_> = (0..6).map(|_| sampled.tick()).collect();
+        assert_eq!(ticks, [false, false, true, false, false, true]);
+        assert!(!OneIn::new(0).tick());
+    }
+
+    #[test]
+    fn size() {
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size("64K"), Ok(64 << 10));
+        assert_eq!(parse_size("2G"), Ok(2 << 30));
+        assert!(parse_size("0").is_err());
+        assert!(parse_size("64KB").is_err());
+        assert!(parse_size("17179869184G").is_err());
+    }
+
+    #[test]
+    fn select() {
+        let registry = registry();
+        assert_eq!(registry.select("empty").len(), 1);
+        assert!(registry.select("atomic/*").len() > 1);
+        assert!(registry.select("unknown").is_empty());
+    }
+}
@@ -0,0 +1,142 @@
+use crate::benchmarks::locks::{McsLock, TicketLock, TtasLock};
+use crate::benchmarks::work::busy_spin;
+use crate::benchmarks::{BenchContext, OneIn, Registry};
+use parking_lot::Mutex;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+
+/// A mutual exclusion lock protecting a counter, benchmarked by incrementing it.
+trait BenchLock: Default + Send + Sync + 'static {
+    fn with_lock(&self, f: impl FnOnce(&mut u64));
+}
+
+impl BenchLock for StdMutex<u64> {
+    #[inline(always)]
+    fn with_lock(&self, f: impl FnOnce(&mut u64)) {
+        f(&mut self.lock().unwrap())
+    }
+}
+
+impl BenchLock for Mutex<u64> {
+    #[inline(always)]
+    fn with_lock(&self, f: impl FnOnce(&mut u64)) {
+        f(&mut self.lock())
+    }
+}
+
+impl BenchLock for TtasLock<u64> {
+    #[inline(always)]
+    fn with_lock(&self, f: impl FnOnce(&mut u64)) {
+        TtasLock::with_lock(self, f)
+    }
+}
+
+impl BenchLock for TicketLock<u64> {
+    #[inline(always)]
+    fn with_lock(&self, f: impl FnOnce(&mut u64)) {
+        TicketLock::with_lock(self, f)
+    }
+}
+
+impl BenchLock for McsLock<u64> {
+    #[inline(always)]
+    fn with_lock(&self, f: impl FnOnce(&mut u64)) {
+        McsLock::with_lock(self, f)
+    }
+}
+
+pub fn register(registry: &mut Registry) -> Result<(), String> {
+    register_lock::<StdMutex<u64>>(registry, "std", "std Mutex")?;
+    register_lock::<Mutex<u64>>(registry, "parking-lot", "parking_lot Mutex")?;
+    register_lock::<TtasLock<u64>>(registry, "ttas", "test-and-test-and-set spinlock")?;
+    register_lock::<TicketLock<u64>>(registry, "ticket", "ticket spinlock")?;
+    register_lock::<McsLock<u64>>(registry, "mcs", "MCS queue lock")?;
+    Ok(())
+}
+
+fn register_lock<L: BenchLock>(
+    registry: &mut Registry,
+    name: &str,
+    description: &str,
+) -> Result<(), String> {
+    registry.register_fn(
+        format!("mutex/{}", name),
+        format!(
+            "All threads increment a counter behind a shared {}",
+            description
+        ),
+        test_contended_lock::<L>,
+    )?;
+    registry.register_fn(
+        format!("mutex/{}-uncontended", name),
+        format!(
+            "Each thread increments a counter behind its own {}",
+            description
+        ),
+        test_uncontended_lock::<L>,
+    )?;
+    Ok(())
+}
+
+/// Counter of the lock holds long enough to have been preempted.
+pub const PREEMPTED_HOLDS: &str = "preempted-holds";
+
+/// One in this many critical sections is timed, keeping the cost of reading the clock
+/// well below that of the lock itself.
+const HOLD_SAMPLE: u64 = 64;
+
+/// Holds this much longer than the critical section work were most likely preempted, a
+/// scheduler time slice being in the milliseconds while no interrupt takes that long.
+const PREEMPTED_HOLD: Duration = Duration::from_micros(50);
+
+/// Increments the counter after the critical section work, then does the think work.
+///
+/// One in `HOLD_SAMPLE` critical sections is timed, and the ones long enough to have been
+/// preempted extrapolated to the `PREEMPTED_HOLDS` counter.
+fn spawn_lock_worker<L: BenchLock>(ctx: &mut BenchContext, lock: Arc<L>) -> Result<(), String> {
+    let critical = ctx.options.critical_work.iterations();
+    let think = ctx.options.think_work.iterations();
+    let preempted_hold =
+        PREEMPTED_HOLD + Duration::from_nanos(ctx.options.critical_work.nanos() as u64);
+    let preempted_holds = ctx.metrics().counter(PREEMPTED_HOLDS)?;
+    ctx.spawn(move |worker| {
+        let mut timed_hold = OneIn::new(HOLD_SAMPLE);
+        worker.spin_then(
+            || {
+                let timed = timed_hold.tick();
+                lock.with_lock(|val| {
+                    let start = timed.then(Instant::now);
+                    busy_spin(critical);
+                    *val += 1;
+                    if start.is_some_and(|start| start.elapsed() > preempted_hold) {
+                        preempted_holds.add(HOLD_SAMPLE);
+                    }
+                });
+            },
+            || busy_spin(think),
+        )
+    });
+    Ok(())
+}
+
+fn test_contended_lock<L: BenchLock>(ctx: &mut BenchContext) -> Result<(), String> {
+    let lock = Arc::new(L::default());
+
+    for _ in 0..ctx.threads {
+        spawn_lock_worker(ctx, lock.clone())?;
+    }
+    Ok(())
+}
+
+fn test_uncontended_lock<L: BenchLock>(ctx: &mut BenchContext) -> Result<(), String> {
+    let mut locks = vec![];
+
+    for _ in 0..ctx.threads {
+        locks.push(Arc::new(L::default()));
+    }
+
+    for lock in locks {
+        spawn_lock_worker(ctx, lock)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,210 @@
+use crate::benchmarks::{BenchContext, Registry};
+use crossbeam::channel as cb;
+use parking_lot::{Condvar, Mutex};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Barrier, OnceLock};
+use std::thread::Thread;
+use std::time::Instant;
+
+/// Turns handed back and forth by a pair, `STOPPED` once the client has exited.
+const PING: u32 = 0;
+const PONG: u32 = 1;
+const STOPPED: u32 = 2;
+
+/// A way for two threads to hand the turn to each other, sleeping in the kernel while waiting.
+trait Handoff: Default + Send + Sync + 'static {
+    const NAME: &'static str;
+    const DESCRIPTION: &'static str;
+
+    /// Called by each side from its own thread, both sides having returned before the first handoff.
+    fn register(&self, _side: u32) {}
+
+    /// Blocks until `side` has the turn, returning false once the pair is stopped.
+    fn wait(&self, side: u32) -> bool;
+
+    /// Gives the turn to `side`, or `STOPPED`, waking it up.
+    fn pass(&self, side: u32);
+}
+
+#[derive(Default)]
+struct CondvarHandoff {
+    turn: Mutex<u32>,
+    cond: Condvar,
+}
+
+impl Handoff for CondvarHandoff {
+    const NAME: &'static str = "condvar";
+    const DESCRIPTION: &'static str = "a parking_lot Condvar";
+
+    fn wait(&self, side: u32) -> bool {
+        let mut turn = self.turn.lock();
+        while *turn != side && *turn != STOPPED {
+            self.cond.wait(&mut turn);
+        }
+        *turn != STOPPED
+    }
+
+    fn pass(&self, side: u32) {
+        *self.turn.lock() = side;
+        self.cond.notify_one();
+    }
+}
+
+#[derive(Default)]
+struct ParkHandoff {
+    turn: AtomicU32,
+    threads: [OnceLock<Thread>; 2],
+}
+
+impl Handoff for ParkHandoff {
+    const NAME: &'static str = "park";
+    const DESCRIPTION: &'static str = "std::thread::park and unpark";
+
+    fn register(&self, side: u32) {
+        self.threads[side as usize]
+            .set(std::thread::current())
+            .unwrap();
+    }
+
+    fn wait(&self, side: u32) -> bool {
+        loop {
+            match self.turn.load(Ordering::Acquire) {
+                STOPPED => return false,
+                turn if turn == side => return true,
+                // Wakes up spuriously, or with the token left by an unpark that came before
+                _ => std::thread::park(),
+            }
+        }
+    }
+
+    fn pass(&self, side: u32) {
+        self.turn.store(side, Ordering::Release);
+        let other = if side == STOPPED { PONG } else { side };
+        // Registered, as both sides register before the first handoff
+        self.threads[other as usize].get().unwrap().unpark();
+    }
+}
+
+/// One zero-capacity channel per side, each send waiting for the matching receive.
+struct ChannelHandoff {
+    sides: [(cb::Sender<u32>, cb::Receiver<u32>); 2],
+}
+
+impl Default for ChannelHandoff {
+    fn default() -> Self {
+        Self {
+            sides: [cb::bounded(0), cb::bounded(0)],
+        }
+    }
+}
+
+impl Handoff for ChannelHandoff {
+    const NAME: &'static str = "channel-zero";
+    const DESCRIPTION: &'static str = "zero-capacity crossbeam channels";
+
+    fn wait(&self, side: u32) -> bool {
+        self.sides[side as usize].1.recv() != Ok(STOPPED)
+    }
+
+    fn pass(&self, side: u32) {
+        let to = if side == STOPPED { PONG } else { side };
+        self.sides[to as usize].0.send(side).unwrap();
+    }
+}
+
+/// The turn itself is the futex word, waited on while it holds the turn of the other side.
+#[derive(Default)]
+struct FutexHandoff {
+    turn: AtomicU32,
+}
+
+impl Handoff for FutexHandoff {
+    const NAME: &'static str = "futex";
+    const DESCRIPTION: &'static str = "raw futex wait and wake";
+
+    fn wait(&self, side: u32) -> bool {
+        loop {
+            match self.turn.load(Ordering::Acquire) {
+                STOPPED => return false,
+                turn if turn == side => return true,
+                // Returns at once if the turn changed since the load
+                turn => unsafe {
+                    libc::syscall(
+                        libc::SYS_futex,
+                        self.turn.as_ptr(),
+                        libc::FUTEX_WAIT | libc::FUTEX_PRIVATE_FLAG,
+                        turn,
+                        std::ptr::null::<libc::timespec>(),
+                    );
+                },
+            }
+        }
+    }
+
+    fn pass(&self, side: u32) {
+        self.turn.store(side, Ordering::Release);
+        unsafe {
+            libc::syscall(
+                libc::SYS_futex,
+                self.turn.as_ptr(),
+                libc::FUTEX_WAKE | libc::FUTEX_PRIVATE_FLAG,
+                1,
+            );
+        }
+    }
+}
+
+pub fn register(registry: &mut Registry) -> Result<(), String> {
+    register_handoff::<CondvarHandoff>(registry)?;
+    register_handoff::<ParkHandoff>(registry)?;
+    register_handoff::<ChannelHandoff>(registry)?;
+    register_handoff::<FutexHandoff>(registry)?;
+    Ok(())
+}
+
+fn register_handoff<H: Handoff>(registry: &mut Registry) -> Result<(), String> {
+    registry.register_fn(
+        format!("pingpong/{}", H::NAME),
+        format!(
+            "Pairs of threads wake each other up in turn through {}, recording the round-trip latency",
+            H::DESCRIPTION
+        ),
+        test_ping_pong::<H>,
+    )?;
+    Ok(())
+}
+
+/// Both sides of a pair count the turns they get, the client records the round-trip latency.
+/// Runs `threads / 2` pairs, at least one, so that an odd or single thread is reported as the
+/// threads of the pairs actually spawned.
+fn test_ping_pong<H: Handoff>(ctx: &mut BenchContext) -> Result<(), String> {
+    for _ in 0..(ctx.threads / 2).max(1) {
+        let handoff = Arc::new(H::default());
+        let registered = Arc::new(Barrier::new(2));
+
+        let server = handoff.clone();
+        let server_registered = registered.clone();
+        ctx.spawn(move |worker| {
+            server.register(PONG);
+            server_registered.wait();
+            while server.wait(PONG) {
+                worker.count(1);
+                server.pass(PING);
+            }
+        });
+
+        ctx.spawn(move |worker| {
+            handoff.register(PING);
+            registered.wait();
+            while !worker.is_stopped() {
+                let start = Instant::now();
+                handoff.pass(PONG);
+                handoff.wait(PING);
+                worker.record_latency(start.elapsed());
+                worker.count(1);
+            }
+            handoff.pass(STOPPED);
+        });
+    }
+    Ok(())
+}
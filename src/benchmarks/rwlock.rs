@@ -0,0 +1,111 @@
+use crate::benchmarks::work::busy_spin;
+use crate::benchmarks::{BenchContext, Registry, XorShift64};
+use parking_lot::RwLock;
+use std::sync::{Arc, RwLock as StdRwLock};
+
+/// A reader-writer lock protecting a counter: writers increment it, readers load it.
+trait BenchRwLock: Default + Send + Sync + 'static {
+    fn with_read(&self, f: impl FnOnce(&u64));
+    fn with_write(&self, f: impl FnOnce(&mut u64));
+}
+
+impl BenchRwLock for StdRwLock<u64> {
+    #[inline(always)]
+    fn with_read(&self, f: impl FnOnce(&u64)) {
+        f(&self.read().unwrap())
+    }
+
+    #[inline(always)]
+    fn with_write(&self, f: impl FnOnce(&mut u64)) {
+        f(&mut self.write().unwrap())
+    }
+}
+
+impl BenchRwLock for RwLock<u64> {
+    #[inline(always)]
+    fn with_read(&self, f: impl FnOnce(&u64)) {
+        f(&self.read())
+    }
+
+    #[inline(always)]
+    fn with_write(&self, f: impl FnOnce(&mut u64)) {
+        f(&mut self.write())
+    }
+}
+
+pub fn register(registry: &mut Registry) -> Result<(), String> {
+    register_rwlock::<StdRwLock<u64>>(registry, "std", "std RwLock")?;
+    register_rwlock::<RwLock<u64>>(registry, "parking-lot", "parking_lot RwLock")?;
+    Ok(())
+}
+
+fn register_rwlock<L: BenchRwLock>(
+    registry: &mut Registry,
+    name: &str,
+    description: &str,
+) -> Result<(), String> {
+    registry.register_fn(
+        format!("rwlock/{}", name),
+        format!(
+            "All threads read or write (see --write-percent) a shared {}",
+            description
+        ),
+        test_contended_rwlock::<L>,
+    )?;
+    registry.register_fn(
+        format!("rwlock/{}-uncontended", name),
+        format!(
+            "Each thread reads or writes (see --write-percent) its own {}",
+            description
+        ),
+        test_uncontended_rwlock::<L>,
+    )?;
+    Ok(())
+}
+
+fn spawn_rwlock_worker<L: BenchRwLock>(ctx: &mut BenchContext, lock: Arc<L>, seed: u64) {
+    let write_percent = ctx.options.write_percent as u64;
+    let critical = ctx.options.critical_work.iterations();
+    let think = ctx.options.think_work.iterations();
+    ctx.spawn(move |worker| {
+        let mut rng = XorShift64(seed);
+        worker.spin_then(
+            || {
+                if rng.next_u64() % 100 < write_percent {
+                    lock.with_write(|val| {
+                        busy_spin(critical);
+                        *val += 1;
+                    });
+                } else {
+                    lock.with_read(|val| {
+                        busy_spin(critical);
+                        std::hint::black_box(*val);
+                    });
+                }
+            },
+            || busy_spin(think),
+        )
+    });
+}
+
+fn test_contended_rwlock<L: BenchRwLock>(ctx: &mut BenchContext) -> Result<(), String> {
+    let lock = Arc::new(L::default());
+
+    for index in 0..ctx.threads {
+        spawn_rwlock_worker(ctx, lock.clone(), index as u64 + 1);
+    }
+    Ok(())
+}
+
+fn test_uncontended_rwlock<L: BenchRwLock>(ctx: &mut BenchContext) -> Result<(), String> {
+    let mut locks = vec![];
+
+    for _ in 0..ctx.threads {
+        locks.push(Arc::new(L::default()));
+    }
+
+    for (index, lock) in locks.into_iter().enumerate() {
+        spawn_rwlock_worker(ctx, lock, index as u64 + 1);
+    }
+    Ok(())
+}
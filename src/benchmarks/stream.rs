@@ -0,0 +1,283 @@
+use crate::benchmarks::{BenchContext, Benchmark, Registry, Unit};
+use std::alloc::{alloc_zeroed, dealloc, Layout};
+use std::hint::black_box;
+
+/// Elements processed between two polls of the stop signal, 256 KiB of each array.
+const CHUNK_ELEMENTS: usize = 32 * 1024;
+
+/// Alignment of the arrays, enough for the non-temporal stores and for whole cache lines.
+const ALIGNMENT: usize = 4096;
+
+/// Scalar of the triad kernel, as in STREAM.
+const SCALAR: f64 = 3.0;
+
+/// STREAM-style kernels, each one moving a fixed number of bytes per element.
+#[derive(Clone, Copy)]
+enum Kernel {
+    /// Sums `a`
+    Read,
+    /// `a[i] = s`
+    Write,
+    /// `b[i] = a[i]`
+    Copy,
+    /// `a[i] = b[i] + s * c[i]`
+    Triad,
+}
+
+impl Kernel {
+    const ALL: [Kernel; 4] = [Kernel::Read, Kernel::Write, Kernel::Copy, Kernel::Triad];
+
+    /// Arrays read or written per element, counted as in STREAM without the write-allocate traffic.
+    fn streams(&self) -> usize {
+        match self {
+            Kernel::Read | Kernel::Write => 1,
+            Kernel::Copy => 2,
+            Kernel::Triad => 3,
+        }
+    }
+}
+
+struct StreamBenchmark(Kernel);
+
+impl Benchmark for StreamBenchmark {
+    fn name(&self) -> &str {
+        match self.0 {
+            Kernel::Read => "stream/read",
+            Kernel::Write => "stream/write",
+            Kernel::Copy => "stream/copy",
+            Kernel::Triad => "stream/triad",
+        }
+    }
+
+    fn description(&self) -> &str {
+        match self.0 {
+            Kernel::Read => "Each thread sums its own --buffer-size array of doubles",
+            Kernel::Write => {
+                "Each thread fills its own --buffer-size array, with non-temporal stores if --non-temporal"
+            }
+            Kernel::Copy => {
+                "Each thread copies between its own --buffer-size arrays, with non-temporal stores if --non-temporal"
+            }
+            Kernel::Triad => {
+                "Each thread computes a = b + s * c on its own --buffer-size arrays, with non-temporal stores if --non-temporal"
+            }
+        }
+    }
+
+    fn run(&self, ctx: &mut BenchContext) -> Result<(), String> {
+        test_stream(ctx, self.0)
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::Bytes
+    }
+
+    fn is_memory_bandwidth(&self) -> bool {
+        true
+    }
+}
+
+pub fn register(registry: &mut Registry) -> Result<(), String> {
+    for kernel in Kernel::ALL {
+        registry.register(StreamBenchmark(kernel))?;
+    }
+    Ok(())
+}
+
+/// Array of doubles aligned to a page, allocated zeroed so that its pages are only
+/// faulted in, and placed on the NUMA node of the worker, by the first write.
+struct AlignedArray {
+    ptr: *mut f64,
+    len: usize,
+    layout: Layout,
+}
+
+impl AlignedArray {
+    fn new(len: usize, value: f64) -> Result<Self, String> {
+        let too_large = || format!("Cannot allocate an array of {} doubles", len);
+        let size = len
+            .max(1)
+            .checked_mul(std::mem::size_of::<f64>())
+            .ok_or_else(too_large)?;
+        let layout = Layout::from_size_align(size, ALIGNMENT).map_err(|_| too_large())?;
+        let ptr = unsafe { alloc_zeroed(layout) } as *mut f64;
+        if ptr.is_null() {
+            return Err(too_large());
+        }
+        let mut array = Self { ptr, len, layout };
+        array.fill(value);
+        Ok(array)
+    }
+}
+
+impl std::ops::Deref for AlignedArray {
+    type Target = [f64];
+
+    fn deref(&self) -> &[f64] {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl std::ops::DerefMut for AlignedArray {
+    fn deref_mut(&mut self) -> &mut [f64] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+}
+
+impl Drop for AlignedArray {
+    fn drop(&mut self) {
+        unsafe { dealloc(self.ptr as *mut u8, self.layout) }
+    }
+}
+
+fn test_stream(ctx: &mut BenchContext, kernel: Kernel) -> Result<(), String> {
+    let len = ctx.options.buffer_size as usize / std::mem::size_of::<f64>();
+    let non_temporal = ctx.options.non_temporal;
+
+    for _ in 0..ctx.threads {
+        ctx.spawn_after_setup(
+            // Allocated by the worker, so that the arrays are local to its cpu. The arrays the
+            // kernel does not stream through are left empty, for a working set of --buffer-size
+            // per stream.
+            move || {
+                let len_of = |stream| if stream < kernel.streams() { len } else { 0 };
+                Ok((
+                    AlignedArray::new(len, 1.0)?,
+                    AlignedArray::new(len_of(1), 2.0)?,
+                    AlignedArray::new(len_of(2), 0.5)?,
+                ))
+            },
+            move |worker, (mut a, mut b, c)| {
+                while !worker.is_stopped() {
+                    for start in (0..len).step_by(CHUNK_ELEMENTS) {
+                        let range = start..(start + CHUNK_ELEMENTS).min(len);
+                        let elements = range.len();
+                        match kernel {
+                            Kernel::Read => {
+                                black_box(sum(&a[range]));
+                            }
+                            Kernel::Write => fill(&mut a[range], SCALAR, non_temporal),
+                            Kernel::Copy => copy(&mut b[range.clone()], &a[range], non_temporal),
+                            Kernel::Triad => triad(
+                                &mut a[range.clone()],
+                                &b[range.clone()],
+                                &c[range],
+                                non_temporal,
+                            ),
+                        }
+                        worker.count(
+                            (elements * kernel.streams() * std::mem::size_of::<f64>()) as u64,
+                        );
+                        if worker.is_stopped() {
+                            break;
+                        }
+                    }
+                }
+                black_box((&a, &b));
+            },
+        );
+    }
+    Ok(())
+}
+
+/// Sums with independent lanes, so that the loop is vectorized despite the floating point additions.
+#[inline(never)]
+fn sum(a: &[f64]) -> f64 {
+    let mut lanes = [0.0; 8];
+    let chunks = a.chunks_exact(lanes.len());
+    let remainder: f64 = chunks.remainder().iter().sum();
+    for chunk in chunks {
+        for (lane, value) in lanes.iter_mut().zip(chunk) {
+            *lane += value;
+        }
+    }
+    lanes.iter().sum::<f64>() + remainder
+}
+
+#[inline(never)]
+fn fill(a: &mut [f64], value: f64, non_temporal: bool) {
+    if non_temporal {
+        nt::store_each(a, |_| value);
+    } else {
+        a.fill(value);
+    }
+}
+
+/// The regular copy compiles to `memcpy`, as the chunk copies of `memory_fs` do,
+/// which may itself switch to non-temporal stores above the size of the last level cache.
+#[inline(never)]
+fn copy(dst: &mut [f64], src: &[f64], non_temporal: bool) {
+    if non_temporal {
+        nt::store_each(dst, |i| src[i]);
+    } else {
+        dst.copy_from_slice(src);
+    }
+}
+
+#[inline(never)]
+fn triad(a: &mut [f64], b: &[f64], c: &[f64], non_temporal: bool) {
+    if non_temporal {
+        nt::store_each(a, |i| b[i] + SCALAR * c[i]);
+    } else {
+        for ((a, b), c) in a.iter_mut().zip(b).zip(c) {
+            *a = b + SCALAR * c;
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod nt {
+    use std::arch::x86_64::{_mm_set_pd, _mm_sfence, _mm_stream_pd};
+
+    /// Stores `value(i)` to each `dst[i]` bypassing the caches, two doubles at a time.
+    /// `dst` must start on a 16 bytes boundary, which holds for the chunks of the aligned arrays.
+    #[inline(always)]
+    pub fn store_each(dst: &mut [f64], value: impl Fn(usize) -> f64) {
+        assert_eq!(dst.as_ptr() as usize % 16, 0);
+        let pairs = dst.len() / 2;
+        let ptr = dst.as_mut_ptr();
+        for pair in 0..pairs {
+            let i = pair * 2;
+            // SSE2 is part of the x86_64 baseline
+            unsafe { _mm_stream_pd(ptr.add(i), _mm_set_pd(value(i + 1), value(i))) };
+        }
+        if dst.len() % 2 == 1 {
+            dst[dst.len() - 1] = value(dst.len() - 1);
+        }
+        unsafe { _mm_sfence() };
+    }
+}
+
+/// Non-temporal stores are only implemented on x86_64, the other targets fall back to regular stores.
+#[cfg(not(target_arch = "x86_64"))]
+mod nt {
+    #[inline(always)]
+    pub fn store_each(dst: &mut [f64], value: impl Fn(usize) -> f64) {
+        for (i, dst) in dst.iter_mut().enumerate() {
+            *dst = value(i);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::benchmarks::stream::{copy, fill, sum, triad, AlignedArray, SCALAR};
+
+    #[test]
+    fn kernels() {
+        for non_temporal in [false, true] {
+            let mut a = AlignedArray::new(1001, 1.0).unwrap();
+            let mut b = AlignedArray::new(1001, 2.0).unwrap();
+            let c = AlignedArray::new(1001, 0.5).unwrap();
+            assert_eq!(sum(&a), 1001.0);
+
+            fill(&mut a, 4.0, non_temporal);
+            assert!(a.iter().all(|&v| v == 4.0));
+            copy(&mut b, &a, non_temporal);
+            assert!(b.iter().all(|&v| v == 4.0));
+            triad(&mut a, &b, &c, non_temporal);
+            assert!(a.iter().all(|&v| v == 4.0 + SCALAR * 0.5));
+        }
+        assert!(AlignedArray::new(usize::MAX / 4, 0.0).is_err());
+    }
+}
@@ -0,0 +1,81 @@
+use std::alloc::{alloc_zeroed, dealloc, Layout};
+use std::marker::PhantomData;
+
+/// Alignment of the allocation, so that the slots keep the same offsets within a page whatever the stride.
+const PAGE_SIZE: usize = 4096;
+
+/// One value per thread, laid out `stride` bytes apart in a single page-aligned allocation.
+///
+/// `T` must be valid when zeroed, which holds for the atomics and plain integers it is used with,
+/// hence private to the benchmarks.
+pub struct StridedSlots<T> {
+    ptr: *mut u8,
+    layout: Layout,
+    count: usize,
+    stride: usize,
+    _marker: PhantomData<T>,
+}
+
+unsafe impl<T: Sync> Sync for StridedSlots<T> {}
+unsafe impl<T: Send> Send for StridedSlots<T> {}
+
+impl<T> StridedSlots<T> {
+    pub fn new(count: usize, stride: usize) -> Result<Self, String> {
+        assert!(
+            stride >= std::mem::size_of::<T>() && stride.is_multiple_of(std::mem::align_of::<T>())
+        );
+        let too_large = || format!("Cannot allocate {} slots {} bytes apart", count, stride);
+        let size = count.max(1).checked_mul(stride).ok_or_else(too_large)?;
+        // Aligned to the stride, rounded up to a power of two, for the larger ones
+        let align = stride.checked_next_power_of_two().ok_or_else(too_large)?;
+        let layout =
+            Layout::from_size_align(size, PAGE_SIZE.max(align)).map_err(|_| too_large())?;
+        let ptr = unsafe { alloc_zeroed(layout) };
+        if ptr.is_null() {
+            return Err(too_large());
+        }
+        Ok(Self {
+            ptr,
+            layout,
+            count,
+            stride,
+            _marker: PhantomData,
+        })
+    }
+
+    #[inline(always)]
+    pub fn get(&self, index: usize) -> &T {
+        assert!(index < self.count);
+        unsafe { &*(self.ptr.add(index * self.stride) as *const T) }
+    }
+}
+
+impl<T> Drop for StridedSlots<T> {
+    fn drop(&mut self) {
+        unsafe {
+            for index in 0..self.count {
+                std::ptr::drop_in_place(self.ptr.add(index * self.stride) as *mut T);
+            }
+            dealloc(self.ptr, self.layout)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::benchmarks::strided::StridedSlots;
+    use std::sync::atomic::AtomicU64;
+
+    #[test]
+    fn layout() {
+        for stride in [8, 64, 4096, 4104, 12288] {
+            let slots = StridedSlots::<AtomicU64>::new(4, stride).unwrap();
+            let base = slots.get(0) as *const _ as usize;
+            assert_eq!(base % 4096, 0);
+            for i in 1..4 {
+                assert_eq!(slots.get(i) as *const _ as usize - base, i * stride);
+            }
+        }
+        assert!(StridedSlots::<AtomicU64>::new(2, usize::MAX / 2 + 9).is_err());
+    }
+}
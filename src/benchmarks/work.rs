@@ -0,0 +1,87 @@
+use serde::{Serialize, Serializer};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+/// Amount of busy work, either in spin iterations or in nanoseconds, converted
+/// to iterations with a calibration done once per process.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Work {
+    Spins(u64),
+    Nanos(u64),
+}
+
+impl FromStr for Work {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let units = [("ns", 1), ("us", 1_000), ("ms", 1_000_000)];
+        let invalid = || format!("Invalid work '{}', expected spins or a time like 200ns", s);
+        for (suffix, scale) in units {
+            if let Some(value) = s.strip_suffix(suffix) {
+                let value: u64 = value.trim().parse().map_err(|_| invalid())?;
+                return value
+                    .checked_mul(scale)
+                    .map(Work::Nanos)
+                    .ok_or_else(invalid);
+            }
+        }
+        s.trim().parse().map(Work::Spins).map_err(|_| invalid())
+    }
+}
+
+impl Display for Work {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Work::Spins(spins) => write!(f, "{}", spins),
+            Work::Nanos(nanos) => write!(f, "{}ns", nanos),
+        }
+    }
+}
+
+impl Serialize for Work {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl Work {
+    pub fn iterations(&self) -> u64 {
+        match *self {
+            Work::Spins(spins) => spins,
+            Work::Nanos(nanos) => (nanos as f64 * spins_per_nanosecond()).round() as u64,
+        }
+    }
+
+    pub fn nanos(&self) -> f64 {
+        match *self {
+            Work::Spins(spins) => spins as f64 / spins_per_nanosecond(),
+            Work::Nanos(nanos) => nanos as f64,
+        }
+    }
+}
+
+#[inline(always)]
+pub fn busy_spin(iterations: u64) {
+    for i in 0..iterations {
+        std::hint::black_box(i);
+    }
+}
+
+fn spins_per_nanosecond() -> f64 {
+    static CALIBRATION: OnceLock<f64> = OnceLock::new();
+    *CALIBRATION.get_or_init(|| {
+        const ITERATIONS: u64 = 1 << 22;
+        // Keep the fastest of a few attempts, the others were likely interrupted
+        let best = (0..5)
+            .map(|_| {
+                let start = Instant::now();
+                busy_spin(ITERATIONS);
+                start.elapsed()
+            })
+            .min()
+            .unwrap();
+        ITERATIONS as f64 / best.as_nanos().max(1) as f64
+    })
+}
@@ -0,0 +1,328 @@
+use crate::benchmarks::{BenchContext, Benchmark, Phase, Registry, Unit};
+use parallel_processor::buckets::concurrent::BucketsThreadDispatcher;
+use parallel_processor::buckets::MultiThreadBuckets;
+use parallel_processor::lock_free_binary_writer::LockFreeBinaryWriter;
+use parallel_processor::memory_data_size::MemoryDataSize;
+use parallel_processor::memory_fs::file::internal::MemoryFileMode;
+use parallel_processor::memory_fs::file::reader::FileReader;
+use parallel_processor::memory_fs::{MemoryFs, RemoveFileMode};
+use rayon::prelude::*;
+use serde::{Serialize, Serializer};
+use std::fmt::{Display, Formatter};
+use std::io::{Read, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Flush queue size of the in-memory file system, as used in production.
+const FLUSH_QUEUE_SIZE: usize = 4096;
+
+/// Elements verified at once during the read phase.
+const READ_BATCH_ELEMENTS: usize = 16384;
+
+/// Storage of the bucket files, mirroring `MemoryFileMode`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileMode {
+    DiskOnly,
+    AlwaysMemory,
+    PreferMemory { swap_priority: usize },
+}
+
+impl FromStr for FileMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "disk-only" => Ok(FileMode::DiskOnly),
+            None if s == "always-memory" => Ok(FileMode::AlwaysMemory),
+            None if s == "prefer-memory" => Ok(FileMode::PreferMemory { swap_priority: 0 }),
+            Some(("prefer-memory", priority)) => priority
+                .parse()
+                .map(|swap_priority| FileMode::PreferMemory { swap_priority })
+                .map_err(|_| format!("Invalid swap priority '{}'", priority)),
+            _ => Err(format!(
+                "Unknown file mode '{}', expected disk-only, always-memory or prefer-memory[:<priority>]",
+                s
+            )),
+        }
+    }
+}
+
+impl Display for FileMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileMode::DiskOnly => write!(f, "disk-only"),
+            FileMode::AlwaysMemory => write!(f, "always-memory"),
+            FileMode::PreferMemory { swap_priority } => {
+                write!(f, "prefer-memory:{}", swap_priority)
+            }
+        }
+    }
+}
+
+impl Serialize for FileMode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl From<FileMode> for MemoryFileMode {
+    fn from(mode: FileMode) -> Self {
+        match mode {
+            FileMode::DiskOnly => MemoryFileMode::DiskOnly,
+            FileMode::AlwaysMemory => MemoryFileMode::AlwaysMemory,
+            FileMode::PreferMemory { swap_priority } => {
+                MemoryFileMode::PreferMemory { swap_priority }
+            }
+        }
+    }
+}
+
+/// Threads dispatching elements to bucket files, counting the bytes written.
+struct WritingBenchmark;
+
+impl Benchmark for WritingBenchmark {
+    fn name(&self) -> &str {
+        "writing/buckets"
+    }
+
+    fn description(&self) -> &str {
+        "Threads dispatch elements to lock-free binary bucket files (see --buckets, --element-size, \
+         --dispatcher-buffer, --fs-memory, --file-mode and --flush-threads)"
+    }
+
+    fn run(&self, ctx: &mut BenchContext) -> Result<(), String> {
+        writing_test(ctx)
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::Bytes
+    }
+}
+
+pub fn register(registry: &mut Registry) -> Result<(), String> {
+    registry.register(WritingBenchmark)?;
+    Ok(())
+}
+
+/// Calls `f` with the standard output redirected to stderr.
+fn stdout_to_stderr<R>(f: impl FnOnce() -> R) -> Result<R, String> {
+    let check = |ret: libc::c_int| match ret {
+        -1 => Err(std::io::Error::last_os_error()),
+        fd => Ok(fd),
+    };
+    let error = |err| format!("Cannot redirect the standard output: {}", err);
+    // Held throughout, being reentrant, so that the other threads cannot write meanwhile
+    let mut stdout = std::io::stdout().lock();
+    stdout.flush().map_err(error)?;
+    let saved = check(unsafe { libc::dup(libc::STDOUT_FILENO) }).map_err(error)?;
+    let result = check(unsafe { libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) })
+        .map(|_| (f(), stdout.flush()));
+    let restored = check(unsafe { libc::dup2(saved, libc::STDOUT_FILENO) });
+    unsafe { libc::close(saved) };
+    let (result, flushed) = result.map_err(error)?;
+    restored.map_err(error)?;
+    flushed.map_err(error)?;
+    Ok(result)
+}
+
+pub fn writing_test(ctx: &mut BenchContext) -> Result<(), String> {
+    let options = ctx.options.clone();
+
+    // The file system prints its settings, which must not end up among the json or csv records
+    stdout_to_stderr(|| {
+        MemoryFs::init(
+            MemoryDataSize::from_octets(options.fs_memory as f64),
+            FLUSH_QUEUE_SIZE,
+            options.flush_threads,
+            0,
+        )
+    })?;
+
+    // Each bucket file is named after this path, suffixed with the index of the bucket
+    let path = options
+        .bucket_dir
+        .clone()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("bucket");
+    let files = Arc::new(MultiThreadBuckets::<LockFreeBinaryWriter>::new(
+        options.buckets,
+        &(path, options.file_mode.into()),
+        None,
+    ));
+
+    let rounds = Arc::new(AtomicU64::new(0));
+    let elements = ctx.metrics().counter("elements")?;
+
+    for index in 0..ctx.threads {
+        let files = files.clone();
+        let rounds = rounds.clone();
+        let elements = elements.clone();
+        ctx.spawn(move |worker| {
+            let mut thread = BucketsThreadDispatcher::<_, [u8]>::new(
+                MemoryDataSize::from_octets(options.dispatcher_buffer as f64),
+                &files,
+            );
+            let mut element = element_pattern(index, options.element_size);
+            let mut thread_rounds = 0;
+            while !worker.is_stopped() {
+                for bucket in 0..options.buckets {
+                    if let Some(tag) = element.first_mut() {
+                        *tag = bucket as u8;
+                    }
+                    thread.add_element(bucket as u16, &(), &element)
+                }
+                thread_rounds += 1;
+                worker.count((options.buckets * options.element_size) as u64);
+                elements.add(options.buckets as u64);
+            }
+            rounds.fetch_add(thread_rounds, Ordering::Relaxed);
+        });
+    }
+
+    // In-memory files keep their chunks until they are removed, and the
+    // allocator waits for all its chunks to come back before terminating.
+    let threads = ctx.threads;
+    ctx.on_finish(move || {
+        let mut files = Arc::try_unwrap(files)
+            .map_err(|_| "Bucket files still referenced after the workers exited".to_string())?;
+        let paths = files.finalize();
+
+        let expected = rounds.load(Ordering::Relaxed);
+        let start = Instant::now();
+        let read = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .map_err(|err| err.to_string())
+            .and_then(|pool| {
+                pool.install(|| {
+                    paths
+                        .par_iter()
+                        .enumerate()
+                        .map(|(bucket, path)| {
+                            let reader = FileReader::open(path)
+                                .ok_or_else(|| format!("Cannot open {}", path.display()))?;
+                            verify_bucket(reader, bucket, options.element_size, expected)
+                                .map_err(|err| format!("{}: {}", path.display(), err))
+                        })
+                        .sum::<Result<u64, String>>()
+                })
+            });
+        let elapsed = start.elapsed();
+
+        for path in paths {
+            let _ = MemoryFs::remove_file(&path, RemoveFileMode::Remove { remove_fs: true });
+        }
+        MemoryFs::terminate();
+
+        Ok(Some(Phase {
+            name: "read",
+            unit: Unit::Bytes,
+            amount: read.map_err(|err| format!("Verification failed, {}", err))?,
+            elapsed,
+        }))
+    });
+    Ok(())
+}
+
+/// Each element starts with the low byte of its bucket, followed by bytes
+/// counting up from the index of the writing thread.
+fn element_pattern(thread: usize, size: usize) -> Vec<u8> {
+    (0..size)
+        .map(|i| (thread as u8).wrapping_add(i.saturating_sub(1) as u8))
+        .collect()
+}
+
+/// Reads a whole bucket, checking that it holds `expected` elements following
+/// `element_pattern`. Returns the number of bytes read.
+fn verify_bucket(
+    mut reader: impl Read,
+    bucket: usize,
+    element_size: usize,
+    expected: u64,
+) -> Result<u64, String> {
+    let mut buffer = vec![0; element_size * READ_BATCH_ELEMENTS];
+    let mut filled = 0;
+    let mut elements = 0;
+    loop {
+        let read = reader
+            .read(&mut buffer[filled..])
+            .map_err(|err| err.to_string())?;
+        filled += read;
+        if read != 0 && filled < buffer.len() {
+            continue;
+        }
+
+        let complete = filled - filled % element_size;
+        for (offset, element) in buffer[..complete].chunks_exact(element_size).enumerate() {
+            let valid = element[0] == bucket as u8
+                && element
+                    .iter()
+                    .skip(1)
+                    .enumerate()
+                    .all(|(i, &byte)| byte == element[1].wrapping_add(i as u8));
+            if !valid {
+                return Err(format!(
+                    "corrupted element {} in bucket {}: {:?}",
+                    elements + offset as u64,
+                    bucket,
+                    element
+                ));
+            }
+        }
+        elements += (complete / element_size) as u64;
+        buffer.copy_within(complete..filled, 0);
+        filled -= complete;
+
+        if read == 0 {
+            break;
+        }
+    }
+
+    if filled != 0 {
+        return Err(format!("{} trailing bytes in bucket {}", filled, bucket));
+    }
+    if elements != expected {
+        return Err(format!(
+            "{} elements in bucket {}, expected {}",
+            elements, bucket, expected
+        ));
+    }
+    Ok(elements * element_size as u64)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::benchmarks::writing_test::{element_pattern, verify_bucket, FileMode};
+
+    #[test]
+    fn file_mode() {
+        assert_eq!("disk-only".parse(), Ok(FileMode::DiskOnly));
+        assert_eq!(
+            "prefer-memory:3".parse(),
+            Ok(FileMode::PreferMemory { swap_priority: 3 })
+        );
+        assert!("memory".parse::<FileMode>().is_err());
+        assert_eq!(FileMode::AlwaysMemory.to_string(), "always-memory");
+    }
+
+    #[test]
+    fn verify() {
+        let element = |thread, bucket| {
+            let mut element = element_pattern(thread, 6);
+            element[0] = bucket;
+            element
+        };
+        let data: Vec<u8> = (0..3).flat_map(|thread| element(thread, 7)).collect();
+
+        assert_eq!(verify_bucket(&data[..], 7, 6, 3), Ok(18));
+        assert!(verify_bucket(&data[..], 7, 6, 4).is_err());
+        assert!(verify_bucket(&data[..], 8, 6, 3).is_err());
+        assert!(verify_bucket(&data[..17], 7, 6, 3).is_err());
+
+        let mut corrupted = data.clone();
+        corrupted[10] ^= 1;
+        assert!(verify_bucket(&corrupted[..], 7, 6, 3).is_err());
+    }
+}
@@ -0,0 +1,54 @@
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicI32, AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Operation counter of a single worker, padded to avoid false sharing with its neighbours,
+/// along with the kernel id of the worker thread once it has started.
+#[repr(align(128))]
+#[derive(Default)]
+pub struct PaddedCounter {
+    ops: AtomicU64,
+    tid: AtomicI32,
+}
+
+impl PaddedCounter {
+    /// Only the owning worker writes the counter, so a plain load and store is enough.
+    #[inline(always)]
+    pub fn add(&self, ops: u64) {
+        self.ops
+            .store(self.ops.load(Ordering::Relaxed) + ops, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.ops.load(Ordering::Relaxed)
+    }
+
+    pub fn set_tid(&self, tid: i32) {
+        self.tid.store(tid, Ordering::Relaxed);
+    }
+
+    /// Id of the worker thread, `None` until it has started.
+    pub fn tid(&self) -> Option<i32> {
+        Some(self.tid.load(Ordering::Relaxed)).filter(|&tid| tid != 0)
+    }
+}
+
+/// The counters of all the workers of a run, in spawn order.
+#[derive(Clone, Default)]
+pub struct ThreadCounters(Arc<Mutex<Vec<Arc<PaddedCounter>>>>);
+
+impl ThreadCounters {
+    pub fn register(&self) -> Arc<PaddedCounter> {
+        let counter = Arc::new(PaddedCounter::default());
+        self.0.lock().push(counter.clone());
+        counter
+    }
+
+    pub fn snapshot(&self) -> Vec<u64> {
+        self.0.lock().iter().map(|c| c.get()).collect()
+    }
+
+    pub fn thread_ids(&self) -> Vec<Option<i32>> {
+        self.0.lock().iter().map(|c| c.tid()).collect()
+    }
+}
@@ -0,0 +1,233 @@
+use crate::affinity::allowed_cpus;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+const CPU_DIR: &str = "/sys/devices/system/cpu";
+
+/// Hardware and system settings a run depends on, so that results of different machines,
+/// or of the same machine differently configured, are never mixed up.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Fingerprint {
+    pub cpu_model: String,
+    /// Online logical cpus
+    pub cpus: usize,
+    /// Cpus this process may run on
+    pub allowed_cpus: usize,
+    pub cores: usize,
+    pub packages: usize,
+    /// SMT siblings of the first cpu, itself included
+    pub threads_per_core: usize,
+    /// Caches of the first cpu
+    pub caches: Vec<Cache>,
+    pub kernel: String,
+    /// Cpu frequency scaling governor of the first cpu
+    pub governor: Option<String>,
+    /// Selected transparent huge pages mode: `always`, `madvise` or `never`
+    pub transparent_hugepages: Option<String>,
+    /// Cpu bandwidth limit of the cgroup, in cpus, if any
+    pub cgroup_cpu_limit: Option<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Cache {
+    pub level: u32,
+    /// `Data`, `Instruction` or `Unified`
+    pub kind: String,
+    pub size: String,
+    /// Cpus sharing this cache
+    pub shared_by: usize,
+}
+
+impl Cache {
+    /// Name in the usual `L1d`, `L1i`, `L2` form.
+    pub fn name(&self) -> String {
+        match self.kind.as_str() {
+            "Data" => format!("L{}d", self.level),
+            "Instruction" => format!("L{}i", self.level),
+            _ => format!("L{}", self.level),
+        }
+    }
+}
+
+impl Fingerprint {
+    pub fn collect() -> Self {
+        let cpu_dirs = cpu_dirs();
+        let cores: HashSet<_> = cpu_dirs
+            .iter()
+            .map(|dir| {
+                (
+                    read_trimmed(dir.join("topology/physical_package_id")),
+                    read_trimmed(dir.join("topology/core_id")),
+                )
+            })
+            .collect();
+        let packages: HashSet<_> = cores.iter().map(|(package, _)| package).collect();
+        let first = Path::new(CPU_DIR).join("cpu0");
+
+        Self {
+            cpu_model: cpu_model().unwrap_or_else(|| "unknown".to_string()),
+            cpus: cpu_dirs.len(),
+            allowed_cpus: allowed_cpus().len(),
+            cores: cores.len(),
+            packages: packages.len(),
+            threads_per_core: read_trimmed(first.join("topology/thread_siblings_list"))
+                .map_or(1, |list| count_cpu_list(&list)),
+            caches: caches(&first),
+            kernel: read_trimmed("/proc/sys/kernel/osrelease")
+                .unwrap_or_else(|| "unknown".to_string()),
+            governor: read_trimmed(first.join("cpufreq/scaling_governor")),
+            transparent_hugepages: read_trimmed("/sys/kernel/mm/transparent_hugepage/enabled")
+                .and_then(|modes| selected_mode(&modes)),
+            cgroup_cpu_limit: cgroup_cpu_limit(),
+        }
+    }
+
+    /// Names of the fields that differ from `other`, empty if the fingerprints match.
+    pub fn differences(&self, other: &Fingerprint) -> Vec<String> {
+        let fields = |fingerprint: &Fingerprint| match serde_json::to_value(fingerprint) {
+            Ok(serde_json::Value::Object(fields)) => fields,
+            _ => serde_json::Map::new(),
+        };
+        let (mine, theirs) = (fields(self), fields(other));
+        mine.iter()
+            .filter(|(name, value)| theirs.get(*name) != Some(value))
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+}
+
+fn read_trimmed(path: impl AsRef<Path>) -> Option<String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .map(|s| s.trim().to_string())
+}
+
+/// Directories of the online cpus, the offline ones having no topology.
+fn cpu_dirs() -> Vec<PathBuf> {
+    let entries = match std::fs::read_dir(CPU_DIR) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.strip_prefix("cpu")
+                .is_some_and(|id| !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()))
+        })
+        .map(|entry| entry.path())
+        .filter(|dir| dir.join("topology").exists())
+        .collect()
+}
+
+fn cpu_model() -> Option<String> {
+    let cpuinfo = std::fs::read_to_string("/proc/cpuinfo").ok()?;
+    cpuinfo.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        // `model name` on x86, `CPU part` being the closest on the arm kernels lacking it
+        matches!(key.trim(), "model name" | "Model" | "CPU part").then(|| value.trim().to_string())
+    })
+}
+
+fn caches(cpu_dir: &Path) -> Vec<Cache> {
+    (0..)
+        .map(|index| cpu_dir.join(format!("cache/index{}", index)))
+        .take_while(|dir| dir.exists())
+        .filter_map(|dir| {
+            Some(Cache {
+                level: read_trimmed(dir.join("level"))?.parse().ok()?,
+                kind: read_trimmed(dir.join("type"))?,
+                size: read_trimmed(dir.join("size"))?,
+                shared_by: read_trimmed(dir.join("shared_cpu_list"))
+                    .map_or(1, |list| count_cpu_list(&list)),
+            })
+        })
+        .collect()
+}
+
+/// Counts the cpus of a list such as `0-3,8,10-11`.
+fn count_cpu_list(list: &str) -> usize {
+    list.split(',')
+        .filter(|range| !range.trim().is_empty())
+        .map(|range| match range.trim().split_once('-') {
+            Some((first, last)) => match (first.parse::<usize>(), last.parse::<usize>()) {
+                (Ok(first), Ok(last)) => last.saturating_sub(first) + 1,
+                _ => 0,
+            },
+            None => 1,
+        })
+        .sum()
+}
+
+/// Reads the bracketed mode out of a sysfs setting such as `always [madvise] never`.
+fn selected_mode(modes: &str) -> Option<String> {
+    let start = modes.find('[')? + 1;
+    let end = start + modes[start..].find(']')?;
+    Some(modes[start..end].to_string())
+}
+
+/// Cpu quota of the cgroup of this process, from `cpu.max` on cgroup v2 or the cfs files on v1.
+fn cgroup_cpu_limit() -> Option<f64> {
+    let cgroups = std::fs::read_to_string("/proc/self/cgroup").ok()?;
+    for line in cgroups.lines() {
+        let mut fields = line.splitn(3, ':');
+        let (_, controllers, path) = (fields.next()?, fields.next()?, fields.next()?);
+        let path = path.trim_start_matches('/');
+        if controllers.is_empty() {
+            let root = Path::new("/sys/fs/cgroup");
+            if let Some(max) = read_trimmed(root.join(path).join("cpu.max")) {
+                return parse_cpu_max(&max);
+            }
+        } else if controllers.split(',').any(|c| c == "cpu") {
+            let root = Path::new("/sys/fs/cgroup/cpu").join(path);
+            let quota = read_trimmed(root.join("cpu.cfs_quota_us"))?;
+            let period = read_trimmed(root.join("cpu.cfs_period_us"))?;
+            return parse_cpu_max(&format!("{} {}", quota, period));
+        }
+    }
+    None
+}
+
+/// Parses `<quota> <period>`, where the quota is `max` or negative when unlimited.
+fn parse_cpu_max(max: &str) -> Option<f64> {
+    let (quota, period) = max.split_once(' ')?;
+    let quota: f64 = quota.parse().ok().filter(|&quota: &f64| quota > 0.0)?;
+    let period: f64 = period.trim().parse().ok()?;
+    Some(quota / period)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::fingerprint::{count_cpu_list, parse_cpu_max, selected_mode, Fingerprint};
+
+    #[test]
+    fn parse() {
+        assert_eq!(count_cpu_list("0-3,8,10-11"), 7);
+        assert_eq!(count_cpu_list("5"), 1);
+        assert_eq!(
+            selected_mode("always [madvise] never"),
+            Some("madvise".to_string())
+        );
+        assert_eq!(selected_mode("always madvise never"), None);
+        assert_eq!(parse_cpu_max("200000 100000"), Some(2.0));
+        assert_eq!(parse_cpu_max("max 100000"), None);
+        assert_eq!(parse_cpu_max("-1 100000"), None);
+    }
+
+    #[test]
+    fn differences() {
+        let fingerprint = Fingerprint::collect();
+        assert!(fingerprint.cpus > 0);
+        assert!(fingerprint.differences(&fingerprint).is_empty());
+        let other = Fingerprint {
+            kernel: "0.0.1".to_string(),
+            governor: Some("test".to_string()),
+            ..fingerprint.clone()
+        };
+        let mut differences = fingerprint.differences(&other);
+        differences.sort();
+        assert_eq!(differences, vec!["governor", "kernel"]);
+    }
+}
@@ -0,0 +1,160 @@
+use crate::affinity::{allowed_cpus, check_allowed, PinPolicy};
+use crate::background::BackgroundSpinners;
+use crate::benchmarks::{BenchContext, BenchOptions, Benchmark};
+use crate::counters::ThreadCounters;
+use crate::latency::LatencyLog;
+use crate::metrics::Metrics;
+use crate::report::{Reporter, RunInfo};
+use crate::setup::SetupLatch;
+use crate::stop_signal::StopSignal;
+use crate::track_cpu::{self, Sample, TrackingOptions};
+use std::any::Any;
+use std::sync::Arc;
+
+/// State shared by the workers of a run and its tracker.
+#[derive(Clone, Default)]
+pub struct RunShared {
+    pub stop: StopSignal,
+    pub counters: ThreadCounters,
+    pub latencies: LatencyLog,
+    pub metrics: Metrics,
+    pub setup: SetupLatch,
+}
+
+/// Samples of a run, with the number of workers the benchmark spawned.
+pub struct RunOutput {
+    pub threads: usize,
+    pub samples: Vec<Sample>,
+}
+
+/// Runs benchmarks one at a time with the shared tracking, pinning and reporting settings.
+pub struct Harness {
+    pub reporter: Arc<Reporter>,
+    pub tracking: TrackingOptions,
+    pub pin: Option<PinPolicy>,
+    pub allowed_cpus: Vec<usize>,
+}
+
+impl Harness {
+    /// Harness without pinning, able to run the workers on all the allowed cpus.
+    pub fn new(reporter: Arc<Reporter>, tracking: TrackingOptions) -> Self {
+        Self {
+            reporter,
+            tracking,
+            pin: None,
+            allowed_cpus: allowed_cpus(),
+        }
+    }
+
+    /// Runs the benchmark until the tracking stops it, reporting its samples and summary.
+    /// Without a tracking cpu, the tracker takes the first allowed cpu left free by the workers.
+    pub fn run(
+        &self,
+        benchmark: &dyn Benchmark,
+        threads: usize,
+        options: &BenchOptions,
+    ) -> Result<RunOutput, String> {
+        if let Some(cpu) = self.tracking.cpu {
+            check_allowed(cpu, &self.allowed_cpus)?;
+        }
+        let worker_cpus = match &self.pin {
+            Some(pin) => Some(pin.assign(&self.allowed_cpus, threads)?),
+            None => None,
+        };
+        let tracker_cpu = self.tracking.cpu.or_else(|| {
+            let worker_cpus = worker_cpus.as_ref()?;
+            self.allowed_cpus
+                .iter()
+                .copied()
+                .find(|cpu| !worker_cpus.contains(cpu))
+        });
+
+        let spinners = match options.background_spinners {
+            0 => None,
+            count => Some(BackgroundSpinners::start(count, options.background_nice)?),
+        };
+        let shared = RunShared::default();
+        let mut ctx = BenchContext::new(
+            threads,
+            options.clone(),
+            shared.clone(),
+            worker_cpus.clone(),
+        );
+        let started = benchmark.run(&mut ctx);
+
+        // Reported with the workers actually spawned, which differ from `threads` for the
+        // benchmarks running pairs or their own producers and consumers
+        let spawned = ctx.spawned();
+        let run = RunInfo {
+            benchmark: benchmark.name().to_string(),
+            threads: spawned,
+            worker_cpus: worker_cpus
+                .map(|cpus| (0..spawned).map(|i| cpus[i % cpus.len()]).collect()),
+            options: options.clone(),
+            unit: benchmark.unit(),
+            memory_bandwidth: benchmark.is_memory_bandwidth(),
+        };
+        let tracker = match started {
+            Ok(()) => Some(track_cpu::start_tracking(
+                run.clone(),
+                self.reporter.clone(),
+                shared.clone(),
+                TrackingOptions {
+                    cpu: tracker_cpu,
+                    ..self.tracking
+                },
+            )),
+            Err(_) => {
+                shared.stop.stop();
+                None
+            }
+        };
+        shared.setup.seal();
+
+        let samples = match tracker {
+            Some(tracker) => tracker
+                .join()
+                .map_err(|panic| thread_panicked("The tracking", panic))
+                .and_then(|samples| samples),
+            None => Ok(vec![]),
+        };
+        if samples.is_err() {
+            shared.stop.stop();
+        }
+        if let Some(spinners) = spinners {
+            spinners.stop();
+        }
+        let phases = ctx.join();
+        started?;
+        let samples = samples?;
+        // A failed run, such as one whose written data does not read back, reports nothing
+        let phases = phases?;
+
+        let write_error = |err| format!("Cannot write the report: {}", err);
+        self.reporter.summary(&run, &samples).map_err(write_error)?;
+        if let Some(percentiles) = shared.latencies.percentiles() {
+            self.reporter
+                .latency(&run, &percentiles)
+                .map_err(write_error)?;
+        }
+        for phase in phases {
+            self.reporter.phase(&run, &phase).map_err(write_error)?;
+        }
+        Ok(RunOutput {
+            threads: spawned,
+            samples,
+        })
+    }
+}
+
+/// Error of a thread that panicked, with the panic message when it has one.
+pub(crate) fn thread_panicked(thread: &str, panic: Box<dyn Any + Send>) -> String {
+    let message = match panic.downcast::<String>() {
+        Ok(message) => *message,
+        Err(panic) => match panic.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "unknown reason".to_string(),
+        },
+    };
+    format!("{} thread panicked: {}", thread, message)
+}
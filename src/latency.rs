@@ -0,0 +1,109 @@
+use crate::stats::LatencyPercentiles;
+use hdrhistogram::Histogram;
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Significant digits kept by the histograms, bounding the relative error to 0.1%.
+const SIGNIFICANT_DIGITS: u8 = 3;
+
+/// Latencies of a run, in nanoseconds, merged from the per-worker histograms when the workers exit.
+#[derive(Clone)]
+pub struct LatencyLog {
+    merged: Arc<Mutex<Histogram<u64>>>,
+    /// Cleared during the warm-up, whose latencies are dropped
+    recording: Arc<AtomicBool>,
+}
+
+impl Default for LatencyLog {
+    fn default() -> Self {
+        Self {
+            merged: Arc::new(Mutex::new(new_histogram())),
+            recording: Arc::default(),
+        }
+    }
+}
+
+impl LatencyLog {
+    pub fn start_recording(&self) {
+        self.recording.store(true, Ordering::Relaxed);
+    }
+
+    pub fn recorder(&self) -> LatencyRecorder {
+        LatencyRecorder {
+            log: self.clone(),
+            histogram: new_histogram(),
+        }
+    }
+
+    /// Percentiles of all the recorded latencies, if any benchmark worker recorded one.
+    pub fn percentiles(&self) -> Option<LatencyPercentiles> {
+        LatencyPercentiles::from_histogram(&self.merged.lock())
+    }
+}
+
+/// Histogram growing to fit the largest recorded value, starting from a single nanosecond.
+fn new_histogram() -> Histogram<u64> {
+    Histogram::new(SIGNIFICANT_DIGITS).expect("Invalid histogram precision")
+}
+
+/// HDR histogram of the latencies of a single worker, so that recording never touches shared state.
+pub struct LatencyRecorder {
+    log: LatencyLog,
+    histogram: Histogram<u64>,
+}
+
+impl LatencyRecorder {
+    #[inline(always)]
+    pub fn record(&mut self, latency: Duration) {
+        if !self.log.recording.load(Ordering::Relaxed) {
+            return;
+        }
+        let nanos = latency.as_nanos().max(1) as u64;
+        // Resizes the histogram on the first latency of a new order of magnitude
+        if self.histogram.record(nanos).is_err() {
+            self.histogram.saturating_record(nanos);
+        }
+    }
+}
+
+impl Drop for LatencyRecorder {
+    fn drop(&mut self) {
+        if !self.histogram.is_empty() {
+            let mut merged = self.log.merged.lock();
+            // As when recording, saturating the values the merged histogram cannot grow to
+            if merged.add(&self.histogram).is_err() {
+                for value in self.histogram.iter_recorded() {
+                    merged.saturating_record_n(value.value_iterated_to(), value.count_at_value());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::latency::LatencyLog;
+    use std::time::Duration;
+
+    #[test]
+    fn merge() {
+        let log = LatencyLog::default();
+        let mut first = log.recorder();
+        let mut second = log.recorder();
+        first.record(Duration::from_secs(1));
+        log.start_recording();
+        for i in 1..=1000 {
+            first.record(Duration::from_nanos(i));
+            second.record(Duration::from_micros(i));
+        }
+        assert!(log.percentiles().is_none());
+        drop(first);
+        drop(second);
+
+        let percentiles = log.percentiles().unwrap();
+        assert_eq!(percentiles.p50, 1000.0);
+        assert!((percentiles.max - 1e6).abs() / 1e6 < 0.001);
+    }
+}
@@ -0,0 +1,57 @@
+//! Harness of the `rust-test` benchmarks, usable by other crates to run their own hot loops
+//! with the same per-thread counting, cpu and throughput sampling, and reporting.
+//!
+//! A custom benchmark spawns its workers through the `BenchContext`, and is run by a `Harness`:
+//!
+//! ```no_run
+//! use rust_test::benchmarks::{BenchContext, BenchOptions, Registry};
+//! use rust_test::harness::Harness;
+//! use rust_test::report::{Format, Reporter};
+//! use rust_test::track_cpu::TrackingOptions;
+//! use std::sync::Arc;
+//!
+//! fn spin(ctx: &mut BenchContext) -> Result<(), String> {
+//!     for _ in 0..ctx.threads {
+//!         ctx.spawn(|worker| {
+//!             while !worker.is_stopped() {
+//!                 worker.count(1);
+//!             }
+//!         });
+//!     }
+//!     Ok(())
+//! }
+//!
+//! fn main() -> Result<(), String> {
+//!     let mut registry = Registry::default();
+//!     registry.register_fn("custom/spin", "Counts as fast as possible", spin)?;
+//!
+//!     let reporter = Reporter::new(Format::Text, None).map_err(|err| err.to_string())?;
+//!     let tracking = TrackingOptions {
+//!         samples: Some(5),
+//!         ..Default::default()
+//!     };
+//!     let harness = Harness::new(Arc::new(reporter), tracking);
+//!     for benchmark in registry.select("custom/*") {
+//!         let output = harness.run(benchmark, 4, &BenchOptions::default())?;
+//!         eprintln!("{} samples", output.samples.len());
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+pub mod affinity;
+pub mod background;
+pub mod baseline;
+pub mod benchmarks;
+pub mod counters;
+pub mod fingerprint;
+pub mod harness;
+pub mod latency;
+pub mod metrics;
+pub mod report;
+pub mod setup;
+pub mod stats;
+pub mod stop_signal;
+pub mod sweep;
+pub mod thread_stats;
+pub mod track_cpu;
@@ -1,327 +1,499 @@
-mod track_cpu;
-mod writing_test;
-
-use std::sync::{Arc, Mutex as StdMutex};
-use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
-use std::thread;
+mod suite;
+
+use crate::suite::Suite;
+use rust_test::affinity::{self, PinPolicy};
+use rust_test::baseline::{self, Baseline, RunResult, Verdict};
+use rust_test::benchmarks::mutex::PREEMPTED_HOLDS;
+use rust_test::benchmarks::{self, BenchOptions, Benchmark, Registry};
+use rust_test::fingerprint::Fingerprint;
+use rust_test::harness::{Harness, RunOutput};
+use rust_test::report::{Format, Reporter};
+use rust_test::stats::Summary;
+use rust_test::sweep::{
+    self, OversubscriptionResult, OversubscriptionSweep, StrideSweep, ThreadSweep, WorkRow,
+    WorkSweep, WorkingSetSweep,
+};
+use rust_test::track_cpu::Sample;
+use rust_test::track_cpu::TrackingOptions;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
-use parking_lot::Mutex;
-use rayon::prelude::*;
 use structopt::StructOpt;
-use crate::track_cpu::set_fn;
-use crate::writing_test::writing_test;
+
+/// Number of samples collected when neither a duration nor a sample count is given.
+const DEFAULT_SAMPLES: usize = 10;
 
 #[derive(StructOpt)]
 struct Args {
-    mode: usize,
-    #[structopt(short)]
-    threads: Option<usize>
+    /// Name of the benchmark to run, or a glob pattern such as `atomic/*`
+    benchmark: Option<String>,
+    /// List the available benchmarks and exit
+    #[structopt(long)]
+    list: bool,
+    #[structopt(short, long)]
+    threads: Option<usize>,
+    /// Run each benchmark once per thread count, either a comma separated list or `pow2`
+    #[structopt(long, conflicts_with = "threads")]
+    sweep: Option<ThreadSweep>,
+    /// Run each lock benchmark once per critical section or think time length,
+    /// as `critical:<list>` or `think:<list>` (e.g. `think:0,100ns,1us`)
+    #[structopt(long, conflicts_with = "sweep")]
+    work_sweep: Option<WorkSweep>,
+    /// Run each strided benchmark once per stride, either a comma separated list of bytes
+    /// or `pow2` for 8 to 4096
+    #[structopt(long, conflicts_with_all = &["sweep", "work-sweep"])]
+    stride_sweep: Option<StrideSweep>,
+    /// Run the pointer chase once per working set, either a comma separated list of sizes
+    /// or `pow2` for 4K to 4G, as long as the buffers of all the threads fit in half of the memory
+    #[structopt(long, conflicts_with_all = &["sweep", "work-sweep", "stride-sweep"])]
+    working_set_sweep: Option<WorkingSetSweep>,
+    /// Run each benchmark with one thread per cpu, then with the threads multiplied by each of
+    /// these factors (e.g. `2,4,8`), reporting how the throughput degrades
+    #[structopt(long, conflicts_with_all = &["sweep", "work-sweep", "stride-sweep", "working-set-sweep"])]
+    oversubscribe: Option<OversubscriptionSweep>,
+    /// Stop each benchmark after this many seconds
+    #[structopt(long, parse(try_from_str = parse_duration))]
+    duration: Option<u64>,
+    /// Seconds each benchmark runs before sampling starts, excluded from the results
+    #[structopt(long, default_value = "1", parse(try_from_str = parse_seconds))]
+    warmup: f64,
+    /// Stop each benchmark after this many one-second samples
+    #[structopt(long)]
+    samples: Option<usize>,
+    /// Output format for the samples and the summary
+    #[structopt(long, default_value = "text", possible_values = &["text", "json", "csv"])]
+    format: Format,
+    /// Write the samples and the summary to this file instead of stdout
+    #[structopt(long)]
+    output: Option<PathBuf>,
+    /// Pin the workers to cpus: `compact`, `scatter` or `list:0,2,4`
+    #[structopt(long)]
+    pin: Option<PinPolicy>,
+    /// Pin the tracking thread to this cpu
+    #[structopt(long)]
+    tracker_cpu: Option<usize>,
+    /// Save the per-interval results of this run as a named baseline
+    #[structopt(long)]
+    save_baseline: Option<String>,
+    /// Compare this run with a saved baseline, exiting with status 2 on a regression
+    #[structopt(long)]
+    compare: Option<String>,
+    /// Directory of the saved baselines
+    #[structopt(long, default_value = "target/baselines")]
+    baseline_dir: PathBuf,
+    /// Slowdown in percent beyond which a significant difference with the baseline is a regression
+    #[structopt(long, default_value = "5", parse(try_from_str = parse_threshold))]
+    regression_threshold: f64,
+    /// Run the benchmarks listed in this toml file one after the other, with their own options,
+    /// instead of a single benchmark (see `suite.rs` for the format)
+    #[structopt(long, conflicts_with = "benchmark")]
+    suite: Option<PathBuf>,
+    #[structopt(flatten)]
+    options: BenchOptions,
 }
 
-fn test_empty(cpu_count: usize) {
-    println!("Test empty loop...");
-    for _ in 0..cpu_count {
-        std::thread::spawn(move || {
-            loop {
-            }
-        });
+fn parse_seconds(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(secs) if secs >= 0.0 && secs.is_finite() => Ok(secs),
+        _ => Err(format!("Invalid number of seconds '{}'", s)),
     }
 }
 
-fn test_atomic_inc(cpu_count: usize) {
-    println!("Test atomic inc...");
-    let atomic_val = Arc::new(AtomicUsize::new(0));
-
-    let atval = atomic_val.clone();
-    set_fn(move || {
-        atval.load(Ordering::SeqCst) as u128
-    });
-
-    for _ in 0..cpu_count {
-
-        let atomic_val = atomic_val.clone();
-        std::thread::spawn(move || {
-            loop {
-                atomic_val.fetch_add(1, Ordering::SeqCst);
-            }
-        });
+fn parse_duration(s: &str) -> Result<u64, String> {
+    match s.parse::<u64>() {
+        Ok(secs) if secs > 0 => Ok(secs),
+        _ => Err(format!(
+            "Invalid duration '{}', expected a positive number of seconds",
+            s
+        )),
     }
 }
 
-fn test_std_mutex(cpu_count: usize) {
-    println!("Test std mutex...");
-    let atomic_val = Arc::new(StdMutex::new(0));
-
-    let atval = atomic_val.clone();
-    set_fn(move || {
-        *atval.lock().unwrap() as u128
-    });
-
-    for _ in 0..cpu_count {
-
-        let atomic_val = atomic_val.clone();
-        std::thread::spawn(move || {
-            loop {
-                *atomic_val.lock().unwrap() += 1;
-            }
-        });
+fn parse_threshold(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(percent) if (0.0..100.0).contains(&percent) => Ok(percent / 100.0),
+        _ => Err(format!("Invalid threshold '{}', expected a percentage", s)),
     }
 }
 
-fn test_plot_mutex(cpu_count: usize) {
-    println!("Test p.lot mutex...");
-    let atomic_val = Arc::new(Mutex::new(0));
-
-    let atval = atomic_val.clone();
-    set_fn(move || {
-        *atval.lock() as u128
-    });
-
-    for _ in 0..cpu_count {
+/// Mean of a per-interval quantity over the samples of a run.
+fn mean_of(samples: &[Sample], f: impl Fn(&Sample) -> f64) -> f64 {
+    Summary::from_samples(&samples.iter().map(f).collect::<Vec<_>>()).mean
+}
 
-        let atomic_val = atomic_val.clone();
-        std::thread::spawn(move || {
-            loop {
-                *atomic_val.lock() += 1;
-            }
-        });
+impl Args {
+    fn tracking_options(&self) -> TrackingOptions {
+        let mut options = TrackingOptions {
+            warmup: Duration::from_secs_f64(self.warmup),
+            duration: self.duration.map(Duration::from_secs),
+            samples: self.samples,
+            cpu: self.tracker_cpu,
+        };
+        if options.duration.is_none() && options.samples.is_none() {
+            options.samples = Some(DEFAULT_SAMPLES);
+        }
+        options
     }
 }
 
-fn test_uncontended_atomic(cpu_count: usize) {
-    println!("Test uncontended atomic...");
-    let mut atomic_vals = vec![];
+fn main() {
+    let args: Args = Args::from_args();
 
-    for _ in 0..cpu_count {
-        atomic_vals.push(Arc::new(AtomicU64::new(0)));
+    let registry = benchmarks::registry();
+
+    if args.list {
+        let width = registry.iter().map(|b| b.name().len()).max().unwrap_or(0);
+        for benchmark in registry.iter() {
+            println!(
+                "{:<width$} {}",
+                benchmark.name(),
+                benchmark.description(),
+                width = width
+            );
+        }
+        return;
     }
 
-    for i in 0..cpu_count {
-        let atomic_val = atomic_vals[i].clone();
-        std::thread::spawn(move || {
-            loop {
-                atomic_val.fetch_add(1, Ordering::SeqCst);
+    let suite = args
+        .suite
+        .as_deref()
+        .map(|path| load_suite(path, &registry));
+    let runs: Vec<(&Args, usize)> = match &suite {
+        Some((entries, _)) => entries.iter().map(|(args, n)| (args, *n)).collect(),
+        None => {
+            let pattern = match &args.benchmark {
+                Some(pattern) => pattern,
+                None => {
+                    eprintln!("No benchmark specified, use --list to show the available ones");
+                    std::process::exit(1);
+                }
+            };
+            if registry.select(pattern).is_empty() {
+                eprintln!("No benchmark matches '{}'!", pattern);
+                std::process::exit(1);
             }
-        });
-    }
-
-    set_fn(move || {
-        let mut result = 0;
-
-        for i in 0..cpu_count {
-            result += atomic_vals[i].load(Ordering::SeqCst) as u128;
+            vec![(&args, 1)]
         }
+    };
 
-        result
+    let reporter = match Reporter::new(args.format, args.output.as_deref()) {
+        Ok(reporter) => Arc::new(reporter),
+        Err(err) => {
+            eprintln!("Cannot open the output: {}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let fingerprint = Fingerprint::collect();
+    write_report(reporter.fingerprint(&fingerprint));
+
+    let compared = args.compare.as_ref().map(|name| {
+        Baseline::load(&args.baseline_dir, name)
+            .and_then(|baseline| {
+                baseline.check_fingerprint(name, &fingerprint)?;
+                Ok(baseline)
+            })
+            .unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            })
     });
-}
-
-#[repr(align(4096))]
-struct Strided(AtomicU64);
 
-fn test_uncontended_atomic_strided(cpu_count: usize) {
-    println!("Test uncontended atomic strided...");
-    let mut atomic_vals = vec![];
-
-    for _ in 0..cpu_count {
-        atomic_vals.push(Arc::new(Strided(AtomicU64::new(0))));
-    }
-
-    for i in 0..cpu_count {
-        let atomic_val = atomic_vals[i].clone();
-        std::thread::spawn(move || {
-            loop {
-                atomic_val.0.fetch_add(1, Ordering::SeqCst);
+    // Fail before running anything rather than when saving the results
+    if let Some(name) = &args.save_baseline {
+        let checked = Baseline::load_existing(&args.baseline_dir, name).and_then(|existing| {
+            match existing.filter(|b| b.fingerprint.is_some()) {
+                Some(baseline) => baseline.check_fingerprint(name, &fingerprint),
+                None => Ok(()),
             }
         });
-    }
-
-    set_fn(move || {
-        let mut result = 0;
-
-        for i in 0..cpu_count {
-            result += atomic_vals[i].0.load(Ordering::SeqCst) as u128;
+        if let Err(err) = checked {
+            eprintln!("{}", err);
+            std::process::exit(1);
         }
-
-        result
-    });
-}
-
-#[repr(align(64))]
-struct Strided64(AtomicU64);
-
-
-fn test_uncontended_atomic_strided64(cpu_count: usize) {
-    println!("Test uncontended atomic strided 64...");
-    let mut atomic_vals = vec![];
-
-    for _ in 0..cpu_count {
-        atomic_vals.push(Arc::new(Strided64(AtomicU64::new(0))));
     }
 
-    for i in 0..cpu_count {
-        let atomic_val = atomic_vals[i].clone();
-        std::thread::spawn(move || {
-            loop {
-                atomic_val.0.fetch_add(1, Ordering::SeqCst);
+    let cooldown = suite
+        .as_ref()
+        .map_or(Duration::ZERO, |(_, cooldown)| *cooldown);
+    let mut results = vec![];
+    for (i, (run_args, repetitions)) in runs.iter().enumerate() {
+        for repetition in 0..*repetitions {
+            if (i > 0 || repetition > 0) && !cooldown.is_zero() {
+                eprintln!("Cooling down for {:?}", cooldown);
+                std::thread::sleep(cooldown);
             }
-        });
+            if suite.is_some() {
+                eprintln!(
+                    "Suite entry {}/{}: {}, repetition {}/{}",
+                    i + 1,
+                    runs.len(),
+                    run_args.benchmark.as_deref().unwrap_or_default(),
+                    repetition + 1,
+                    repetitions
+                );
+            }
+            run_benchmarks(run_args, &registry, &reporter, &mut results);
+        }
     }
 
-    set_fn(move || {
-        let mut result = 0;
+    if let Some(path) = &args.suite {
+        write_report(reporter.suite_table(&path.display().to_string(), &results));
+    }
 
-        for i in 0..cpu_count {
-            result += atomic_vals[i].0.load(Ordering::SeqCst) as u128;
+    if let Some(name) = &args.save_baseline {
+        if let Err(err) = Baseline::save(&args.baseline_dir, name, &fingerprint, &results) {
+            eprintln!("{}", err);
+            std::process::exit(1);
         }
-
-        result
-    });
-}
-
-
-#[repr(align(4096))]
-#[derive(Copy, Clone)]
-struct IntStrided(u64);
-
-fn test_uncontended_integer_non_strided(cpu_count: usize) {
-    println!("Test uncontended integer non strided...");
-    static mut VALS: [u64; 1024] = [0; 1024];
-
-    for i in 0..cpu_count {
-        let refer = unsafe { &mut VALS[i] };
-        std::thread::spawn(move || {
-            loop {
-                unsafe {
-                    let val = std::ptr::read_volatile(refer as *const u64) + 1;
-                    std::ptr::write_volatile(refer as *mut u64, val);
-                }
-            }
-        });
+        eprintln!("Saved baseline {}", name);
     }
 
-    set_fn(move || {
-        let mut result = 0;
-
-        for i in 0..cpu_count {
-            result += unsafe { std::ptr::read_volatile(&VALS[i] as *const u64) as u128 };
+    if let (Some(name), Some(baseline)) = (&args.compare, compared) {
+        let comparisons = baseline::compare(&baseline, &results, args.regression_threshold);
+        write_report(reporter.comparison_table(name, &comparisons));
+        if comparisons.iter().any(|c| c.verdict == Verdict::Regressed) {
+            eprintln!("Performance regression against baseline {}", name);
+            std::process::exit(2);
         }
-
-        result
-    });
+    }
 }
 
-
-fn test_uncontended_integer_strided(cpu_count: usize) {
-    println!("Test uncontended integer non strided...");
-    static mut VALS: [IntStrided; 1024] = [IntStrided(0); 1024];
-
-    for i in 0..cpu_count {
-        let refer = unsafe { &mut VALS[i].0 };
-        std::thread::spawn(move || {
-            loop {
-                unsafe {
-                    let val = std::ptr::read_volatile(refer as *const u64) + 1;
-                    std::ptr::write_volatile(refer as *mut u64, val);
-                }
-            }
+/// Loads a suite and parses its entries into the arguments of their runs, with their repetitions,
+/// exiting before anything runs if the command line has benchmark options, or if an entry is
+/// invalid or matches no benchmark.
+fn load_suite(path: &Path, registry: &Registry) -> (Vec<(Args, usize)>, Duration) {
+    let suite = suite::check_command_line(std::env::args().skip(1))
+        .and_then(|_| Suite::load(path))
+        .and_then(|suite| {
+            let cooldown = suite.cooldown;
+            Ok((resolve_suite(suite, registry)?, cooldown))
         });
-    }
-
-    set_fn(move || {
-        let mut result = 0;
-
-        for i in 0..cpu_count {
-            result += unsafe { std::ptr::read_volatile(&VALS[i].0 as *const u64) as u128 };
-        }
-
-        result
-    });
+    suite.unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    })
 }
 
-fn test_uncontended_pmutex(cpu_count: usize) {
-    println!("Test p.lot uncontended mutex...");
-    let mut atomic_vals = vec![];
-
-    for i in 0..cpu_count {
-        atomic_vals.push(Arc::new(Mutex::new(0)));
-    }
-
-    for i in 0..cpu_count {
-
-        let atomic_val = atomic_vals[i].clone();
-        std::thread::spawn(move || {
-            loop {
-                *atomic_val.lock() += 1;
+fn resolve_suite(suite: Suite, registry: &Registry) -> Result<Vec<(Args, usize)>, String> {
+    suite
+        .entries
+        .into_iter()
+        .map(|entry| {
+            let program = std::iter::once("rust-test".to_string());
+            let args = Args::from_iter_safe(program.chain(entry.args)).map_err(|err| {
+                let message = err.message.lines().next().unwrap_or_default();
+                format!("Invalid suite entry '{}': {}", entry.name, message)
+            })?;
+            if registry.select(&entry.name).is_empty() {
+                return Err(format!(
+                    "No benchmark matches suite entry '{}'!",
+                    entry.name
+                ));
             }
-        });
-    }
-
-    set_fn(move || {
-        let mut result = 0;
-
-        for i in 0..cpu_count {
-            result += *atomic_vals[i].lock() as u128;
-        }
-
-        result
-    });
+            Ok((args, entry.repetitions))
+        })
+        .collect()
 }
 
+/// Exits if the report cannot be written, the output being useless from then on.
+fn write_report(result: std::io::Result<()>) {
+    if let Err(err) = result {
+        eprintln!("Cannot write the report: {}", err);
+        std::process::exit(1);
+    }
+}
 
-fn main() {
-
-    let args: Args = Args::from_args();
-
+/// Runs the benchmarks selected by `args`, adding their results to `results`.
+fn run_benchmarks(
+    args: &Args,
+    registry: &Registry,
+    reporter: &Arc<Reporter>,
+    results: &mut Vec<RunResult>,
+) {
+    let selected = registry.select(args.benchmark.as_deref().unwrap_or_default());
     let cpu_count = args.threads.unwrap_or(num_cpus::get());
 
-    println!("Testing {} cpus!", cpu_count);
-
-    track_cpu::start_tracking();
-
-    match args.mode {
-        0 => {
-            test_empty(cpu_count)
-        }
-        1 => {
-            test_atomic_inc(cpu_count)
+    let harness = Harness {
+        reporter: reporter.clone(),
+        tracking: args.tracking_options(),
+        pin: args.pin.clone(),
+        allowed_cpus: affinity::allowed_cpus(),
+    };
+
+    let mut run = |benchmark: &dyn Benchmark, threads, options: &BenchOptions| {
+        eprintln!("Running {}: {}", benchmark.name(), benchmark.description());
+        let output = harness
+            .run(benchmark, threads, options)
+            .unwrap_or_else(|err| {
+                eprintln!("Cannot run {}: {}", benchmark.name(), err);
+                std::process::exit(1);
+            });
+        let result = RunResult::new(
+            benchmark.name(),
+            output.threads,
+            options,
+            benchmark.unit(),
+            output.samples.iter().map(|s| s.ops_per_sec).collect(),
+        );
+        // The repetitions of a suite entry add up to a single result
+        match results.iter_mut().find(|r| r.same_run(&result)) {
+            Some(repeated) => repeated.ops_per_sec.extend(result.ops_per_sec),
+            None => results.push(result),
         }
-        2 => {
-            test_std_mutex(cpu_count)
-        }
-        3 => {
-            test_plot_mutex(cpu_count)
-        }
-        4 => {
-            test_uncontended_pmutex(cpu_count)
-        }
-        5 => {
-            test_uncontended_atomic(cpu_count)
-        }
-        6 => {
-            test_uncontended_atomic_strided(cpu_count)
+        output
+    };
+
+    match (
+        &args.sweep,
+        &args.work_sweep,
+        &args.stride_sweep,
+        &args.working_set_sweep,
+        &args.oversubscribe,
+    ) {
+        (None, None, None, None, None) => {
+            eprintln!("Testing {} cpus!", cpu_count);
+            for benchmark in selected {
+                run(benchmark, cpu_count, &args.options);
+            }
         }
-        7 => {
-            test_uncontended_integer_non_strided(cpu_count)
+        (None, None, None, None, Some(oversubscribe)) => {
+            let max_factor = oversubscribe.factors().into_iter().max().unwrap_or(1);
+            if cpu_count.checked_mul(max_factor).is_none() {
+                eprintln!("Cannot oversubscribe {} cpus by {}", cpu_count, max_factor);
+                std::process::exit(1);
+            }
+            eprintln!(
+                "Oversubscribing {} cpus by {:?}",
+                cpu_count,
+                oversubscribe.factors()
+            );
+            for benchmark in selected {
+                let results: Vec<_> = oversubscribe
+                    .factors()
+                    .into_iter()
+                    .map(|factor| {
+                        let RunOutput { threads, samples } =
+                            run(benchmark, cpu_count * factor, &args.options);
+                        let has_preempted_holds = samples
+                            .iter()
+                            .any(|s| s.metrics.iter().any(|m| m.name == PREEMPTED_HOLDS));
+                        OversubscriptionResult {
+                            factor,
+                            threads,
+                            ops_per_sec: mean_of(&samples, |s| s.ops_per_sec),
+                            involuntary_switches_per_sec: mean_of(&samples, |s| {
+                                s.involuntary_switches_per_sec()
+                            }),
+                            preempted_holds_per_sec: has_preempted_holds.then(|| {
+                                mean_of(&samples, |s| {
+                                    let holds =
+                                        s.metrics.iter().find(|m| m.name == PREEMPTED_HOLDS);
+                                    holds.map_or(0.0, |m| m.value)
+                                })
+                            }),
+                        }
+                    })
+                    .collect();
+                write_report(reporter.oversubscription_table(
+                    benchmark.name(),
+                    benchmark.unit(),
+                    &sweep::oversubscription_rows(results),
+                ));
+            }
         }
-        8 => {
-            test_uncontended_integer_strided(cpu_count)
+        (None, None, None, Some(working_set_sweep), _) => {
+            eprintln!("Testing {} cpus!", cpu_count);
+            let sizes = working_set_sweep.sizes(cpu_count, sweep::working_set_budget());
+            for benchmark in selected {
+                let results: Vec<_> = sizes
+                    .iter()
+                    .map(|&working_set| {
+                        let options = BenchOptions {
+                            working_set,
+                            ..args.options.clone()
+                        };
+                        let samples = run(benchmark, cpu_count, &options).samples;
+                        (working_set, mean_of(&samples, |s| s.ops_per_sec))
+                    })
+                    .collect();
+                write_report(reporter.working_set_table(
+                    benchmark.name(),
+                    &sweep::working_set_rows(cpu_count, &results),
+                ));
+            }
         }
-        9 => {
-            test_uncontended_atomic_strided64(cpu_count)
+        (None, None, Some(StrideSweep(strides)), _, _) => {
+            eprintln!("Testing {} cpus!", cpu_count);
+            for benchmark in selected {
+                let results: Vec<_> = strides
+                    .iter()
+                    .map(|&stride| {
+                        let options = BenchOptions {
+                            stride,
+                            ..args.options.clone()
+                        };
+                        let samples = run(benchmark, cpu_count, &options).samples;
+                        (stride, mean_of(&samples, |s| s.ops_per_sec))
+                    })
+                    .collect();
+                write_report(
+                    reporter
+                        .stride_table(benchmark.name(), &sweep::stride_rows(cpu_count, &results)),
+                );
+            }
         }
-        10 => {
-            writing_test(cpu_count)
+        (None, Some(work_sweep), _, _, _) => {
+            eprintln!("Testing {} cpus!", cpu_count);
+            for benchmark in selected {
+                let rows: Vec<_> = work_sweep
+                    .values
+                    .iter()
+                    .map(|&work| {
+                        let options = work_sweep.apply(&args.options, work);
+                        let RunOutput { threads, samples } = run(benchmark, cpu_count, &options);
+                        let ops_per_sec = mean_of(&samples, |s| s.ops_per_sec);
+                        WorkRow::new(work, threads, &options, ops_per_sec)
+                    })
+                    .collect();
+                write_report(reporter.work_table(benchmark.name(), work_sweep.target, &rows));
+            }
         }
-        _ => {
-            println!("Unsupported!");
-            return;
+        (Some(sweep), _, _, _, _) => {
+            let thread_counts = sweep.thread_counts(cpu_count);
+            eprintln!("Sweeping over {:?} threads", thread_counts);
+            for benchmark in selected {
+                let results: Vec<_> = thread_counts
+                    .iter()
+                    .map(|&threads| {
+                        let RunOutput { threads, samples } = run(benchmark, threads, &args.options);
+                        (threads, mean_of(&samples, |s| s.ops_per_sec))
+                    })
+                    .collect();
+                write_report(reporter.scaling_table(
+                    benchmark.name(),
+                    benchmark.unit(),
+                    &sweep::scaling_rows(&results),
+                ));
+            }
         }
     }
+}
 
-    loop {
-        thread::sleep(Duration::from_millis(1000));
+#[cfg(test)]
+mod test {
+    use crate::resolve_suite;
+    use crate::suite::Suite;
+    use rust_test::benchmarks;
+    use std::path::Path;
+
+    #[test]
+    fn example_suite() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("suites/example.toml");
+        let suite = Suite::load(&path).unwrap();
+        let runs = resolve_suite(suite, &benchmarks::registry()).unwrap();
+        assert_eq!(runs.len(), 3);
+        assert_eq!(runs[0].1, 3);
+        assert_eq!(runs[2].0.threads, Some(4));
     }
-
-    println!("Hello, world!");
 }
@@ -0,0 +1,187 @@
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Monotonic count shared by any number of threads, reported as a rate per interval.
+///
+/// Every update is an atomic add on a shared cache line: hot loops should count their
+/// operations with `Worker::count` and keep these for secondary quantities.
+#[derive(Clone, Default)]
+pub struct Counter(Arc<AtomicU64>);
+
+impl Counter {
+    #[inline(always)]
+    pub fn add(&self, value: u64) {
+        self.0.fetch_add(value, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Instantaneous value updated by the benchmark, reported as is at each interval.
+#[derive(Clone, Default)]
+pub struct Gauge(Arc<AtomicI64>);
+
+impl Gauge {
+    #[inline(always)]
+    pub fn add(&self, delta: i64) {
+        self.0.fetch_add(delta, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> i64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetricKind {
+    Counter,
+    Gauge,
+}
+
+enum Source {
+    Counter(Counter),
+    Gauge(Gauge),
+    /// Gauge read by calling a function from the tracking thread, such as the length of a queue
+    GaugeFn(Box<dyn Fn() -> f64 + Send + Sync>),
+}
+
+impl Source {
+    fn kind(&self) -> MetricKind {
+        match self {
+            Source::Counter(_) => MetricKind::Counter,
+            Source::Gauge(_) | Source::GaugeFn(_) => MetricKind::Gauge,
+        }
+    }
+
+    fn value(&self) -> f64 {
+        match self {
+            Source::Counter(counter) => counter.get() as f64,
+            Source::Gauge(gauge) => gauge.get() as f64,
+            Source::GaugeFn(f) => f(),
+        }
+    }
+}
+
+/// Value of a metric over one interval: the rate of a counter or the last value of a gauge.
+#[derive(Clone, Debug, Serialize)]
+pub struct MetricSample {
+    pub name: String,
+    pub kind: MetricKind,
+    pub value: f64,
+}
+
+/// Named counters and gauges of a run, registered by the benchmark and sampled by the tracking thread.
+#[derive(Clone, Default)]
+pub struct Metrics(Arc<Mutex<Vec<(String, Source)>>>);
+
+impl Metrics {
+    /// Returns the counter with this name, registering it on first use.
+    pub fn counter(&self, name: &str) -> Result<Counter, String> {
+        let mut metrics = self.0.lock();
+        match metrics.iter().find(|(n, _)| n == name) {
+            Some((_, Source::Counter(counter))) => Ok(counter.clone()),
+            Some(_) => Err(format!("Metric {} is not a counter", name)),
+            None => {
+                let counter = Counter::default();
+                metrics.push((name.to_string(), Source::Counter(counter.clone())));
+                Ok(counter)
+            }
+        }
+    }
+
+    /// Returns the gauge with this name, registering it on first use.
+    pub fn gauge(&self, name: &str) -> Result<Gauge, String> {
+        let mut metrics = self.0.lock();
+        match metrics.iter().find(|(n, _)| n == name) {
+            Some((_, Source::Gauge(gauge))) => Ok(gauge.clone()),
+            Some(_) => Err(format!("Metric {} is not a gauge", name)),
+            None => {
+                let gauge = Gauge::default();
+                metrics.push((name.to_string(), Source::Gauge(gauge.clone())));
+                Ok(gauge)
+            }
+        }
+    }
+
+    /// Registers a gauge computed by `read` each time the metrics are sampled.
+    pub fn gauge_fn(
+        &self,
+        name: &str,
+        read: impl Fn() -> f64 + Send + Sync + 'static,
+    ) -> Result<(), String> {
+        let mut metrics = self.0.lock();
+        if metrics.iter().any(|(n, _)| n == name) {
+            return Err(format!("Duplicate metric name {}", name));
+        }
+        metrics.push((name.to_string(), Source::GaugeFn(Box::new(read))));
+        Ok(())
+    }
+
+    /// Raw values of all the metrics, in registration order: the total of the counters.
+    pub fn snapshot(&self) -> Vec<MetricSample> {
+        self.0
+            .lock()
+            .iter()
+            .map(|(name, source)| MetricSample {
+                name: name.clone(),
+                kind: source.kind(),
+                value: source.value(),
+            })
+            .collect()
+    }
+
+    /// Turns two snapshots into interval values, the counters becoming rates per second.
+    pub fn interval(
+        previous: &[MetricSample],
+        current: Vec<MetricSample>,
+        secs: f64,
+    ) -> Vec<MetricSample> {
+        current
+            .into_iter()
+            .enumerate()
+            .map(|(i, mut metric)| {
+                if metric.kind == MetricKind::Counter {
+                    let last = previous.get(i).map_or(0.0, |m| m.value);
+                    metric.value = (metric.value - last) / secs;
+                }
+                metric
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::metrics::{MetricKind, Metrics};
+
+    #[test]
+    fn interval() {
+        let metrics = Metrics::default();
+        let bytes = metrics.counter("bytes").unwrap();
+        let depth = metrics.gauge("depth").unwrap();
+        bytes.add(100);
+        let previous = metrics.snapshot();
+
+        metrics.counter("bytes").unwrap().add(300);
+        depth.add(7);
+        metrics.gauge_fn("constant", || 1.5).unwrap();
+        assert!(metrics.gauge("bytes").is_err());
+        assert!(metrics.gauge_fn("depth", || 0.0).is_err());
+
+        let interval = Metrics::interval(&previous, metrics.snapshot(), 2.0);
+        let values: Vec<_> = interval
+            .iter()
+            .map(|m| (m.name.as_str(), m.value))
+            .collect();
+        assert_eq!(
+            values,
+            [("bytes", 150.0), ("depth", 7.0), ("constant", 1.5)]
+        );
+        assert_eq!(interval[2].kind, MetricKind::Gauge);
+    }
+}
@@ -0,0 +1,963 @@
+use crate::baseline::{Comparison, RunResult};
+use crate::benchmarks::{BenchOptions, Phase, Unit};
+use crate::fingerprint::Fingerprint;
+use crate::metrics::{MetricKind, MetricSample};
+use crate::stats::{self, LatencyPercentiles, Summary};
+use crate::sweep::{
+    false_sharing_granularity, OversubscriptionRow, ScalingRow, StrideRow, WorkRow, WorkTarget,
+    WorkingSetRow,
+};
+use crate::thread_stats::ThreadCpu;
+use crate::track_cpu::Sample;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!(
+                "Unknown format '{}', expected text, json or csv",
+                s
+            )),
+        }
+    }
+}
+
+/// Identifies the benchmark run the reported records belong to.
+#[derive(Clone)]
+pub struct RunInfo {
+    pub benchmark: String,
+    pub threads: usize,
+    /// Cpu of each worker, if they are pinned
+    pub worker_cpus: Option<Vec<usize>>,
+    pub options: BenchOptions,
+    pub unit: Unit,
+    /// The throughput is a memory bandwidth, summarized in GB/s too
+    pub memory_bandwidth: bool,
+}
+
+/// A single output row, shared by the per-interval samples and the end-of-run summary.
+#[derive(Serialize)]
+struct Record<'a> {
+    kind: &'static str,
+    stat: Option<&'static str>,
+    timestamp: f64,
+    benchmark: &'a str,
+    threads: usize,
+    cpu_user: f64,
+    cpu_system: f64,
+    ops_per_sec: f64,
+    unit: Unit,
+    worker_cpus: Option<&'a [usize]>,
+    thread_ops_per_sec: Vec<f64>,
+    fairness: f64,
+    min_thread_ops_per_sec: f64,
+    max_thread_ops_per_sec: f64,
+    options: &'a BenchOptions,
+    cumulative_ops_per_sec: f64,
+    /// Indices of the samples flagged as outliers, in summaries
+    outliers: Option<&'a [usize]>,
+    metrics: Vec<MetricSample>,
+    /// Cpu utilisation of each worker thread, from /proc/self/task
+    thread_cpu_user: Vec<f64>,
+    thread_cpu_system: Vec<f64>,
+    threads_running: f64,
+    threads_sleeping: f64,
+    voluntary_switches_per_sec: f64,
+    involuntary_switches_per_sec: f64,
+}
+
+/// One latency percentile of a run, sharing the leading columns of `Record` in csv.
+#[derive(Serialize)]
+struct LatencyRecord<'a> {
+    kind: &'static str,
+    stat: &'static str,
+    timestamp: f64,
+    benchmark: &'a str,
+    threads: usize,
+    latency_ns: f64,
+    options: &'a BenchOptions,
+}
+
+/// Throughput of a phase run after the workers, sharing the leading columns of `Record` in csv.
+#[derive(Serialize)]
+struct PhaseRecord<'a> {
+    kind: &'static str,
+    stat: &'static str,
+    timestamp: f64,
+    benchmark: &'a str,
+    threads: usize,
+    ops_per_sec: f64,
+    unit: Unit,
+    amount: u64,
+    elapsed_secs: f64,
+    options: &'a BenchOptions,
+}
+
+/// Throughput of a run of a suite over all its repetitions, sharing the leading columns of `Record` in csv.
+#[derive(Serialize)]
+struct SuiteRecord<'a> {
+    kind: &'static str,
+    stat: &'static str,
+    timestamp: f64,
+    suite: &'a str,
+    benchmark: &'a str,
+    threads: usize,
+    ops_per_sec: f64,
+    unit: Unit,
+    samples: usize,
+    options: &'a serde_json::Value,
+}
+
+/// System the runs of this process are done on, written once before them.
+#[derive(Serialize)]
+struct FingerprintRecord<'a> {
+    kind: &'static str,
+    timestamp: f64,
+    #[serde(flatten)]
+    fingerprint: &'a Fingerprint,
+}
+
+const CSV_HEADER: &str = "kind,stat,timestamp,benchmark,threads,cpu_user,cpu_system,ops_per_sec,\
+    unit,worker_cpus,thread_ops_per_sec,fairness,min_thread_ops_per_sec,max_thread_ops_per_sec,options,\
+    latency_ns,cumulative_ops_per_sec,outliers,metrics,thread_cpu_user,thread_cpu_system,\
+    threads_running,threads_sleeping,voluntary_switches_per_sec,involuntary_switches_per_sec,\
+    suite,samples";
+
+impl Record<'_> {
+    fn write_csv(&self, out: &mut dyn Write) -> std::io::Result<()> {
+        writeln!(
+            out,
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},,{},{},{},{},{},{},{},{},{},,",
+            self.kind,
+            self.stat.unwrap_or(""),
+            self.timestamp,
+            csv_escape(self.benchmark),
+            self.threads,
+            self.cpu_user,
+            self.cpu_system,
+            self.ops_per_sec,
+            self.unit.rate_label(),
+            self.worker_cpus.map(join_csv_list).unwrap_or_default(),
+            join_csv_list(&self.thread_ops_per_sec),
+            self.fairness,
+            self.min_thread_ops_per_sec,
+            self.max_thread_ops_per_sec,
+            csv_options(self.options)?,
+            self.cumulative_ops_per_sec,
+            self.outliers.map(join_csv_list).unwrap_or_default(),
+            csv_escape(
+                &self
+                    .metrics
+                    .iter()
+                    .map(|m| format!("{}={}", m.name, m.value))
+                    .collect::<Vec<_>>()
+                    .join(";")
+            ),
+            join_csv_list(&self.thread_cpu_user),
+            join_csv_list(&self.thread_cpu_system),
+            self.threads_running,
+            self.threads_sleeping,
+            self.voluntary_switches_per_sec,
+            self.involuntary_switches_per_sec
+        )
+    }
+}
+
+impl PhaseRecord<'_> {
+    fn write_csv(&self, out: &mut dyn Write) -> std::io::Result<()> {
+        writeln!(
+            out,
+            "{},{},{},{},{},,,{},{},,,,,,{},,,,,,,,,,,,",
+            self.kind,
+            self.stat,
+            self.timestamp,
+            csv_escape(self.benchmark),
+            self.threads,
+            self.ops_per_sec,
+            self.unit.rate_label(),
+            csv_options(self.options)?
+        )
+    }
+}
+
+impl SuiteRecord<'_> {
+    fn write_csv(&self, out: &mut dyn Write) -> std::io::Result<()> {
+        writeln!(
+            out,
+            "{},{},{},{},{},,,{},{},,,,,,{},,,,,,,,,,,{},{}",
+            self.kind,
+            self.stat,
+            self.timestamp,
+            csv_escape(self.benchmark),
+            self.threads,
+            self.ops_per_sec,
+            self.unit.rate_label(),
+            csv_fields(self.options)?,
+            csv_escape(self.suite),
+            self.samples
+        )
+    }
+}
+
+impl FingerprintRecord<'_> {
+    /// Written as `key=value` pairs in the options column, as the benchmark options are.
+    fn write_csv(&self, out: &mut dyn Write) -> std::io::Result<()> {
+        writeln!(
+            out,
+            "{},,{},,,,,,,,,,,,{},,,,,,,,,,,,",
+            self.kind,
+            self.timestamp,
+            csv_fields(self.fingerprint)?
+        )
+    }
+}
+
+impl LatencyRecord<'_> {
+    fn write_csv(&self, out: &mut dyn Write) -> std::io::Result<()> {
+        writeln!(
+            out,
+            "{},{},{},{},{},,,,,,,,,,{},{},,,,,,,,,,,",
+            self.kind,
+            self.stat,
+            self.timestamp,
+            csv_escape(self.benchmark),
+            self.threads,
+            csv_options(self.options)?,
+            self.latency_ns
+        )
+    }
+}
+
+/// Writes samples and summaries to stdout or to a file, in the selected format.
+pub struct Reporter {
+    format: Format,
+    out: Mutex<Box<dyn Write + Send>>,
+}
+
+impl Reporter {
+    pub fn new(format: Format, output: Option<&Path>) -> std::io::Result<Self> {
+        let mut out: Box<dyn Write + Send> = match output {
+            Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+            None => Box::new(std::io::stdout()),
+        };
+        if format == Format::Csv {
+            writeln!(out, "{}", CSV_HEADER)?;
+        }
+        Ok(Self {
+            format,
+            out: Mutex::new(out),
+        })
+    }
+
+    pub fn sample(&self, run: &RunInfo, sample: &Sample) -> std::io::Result<()> {
+        let mut out = self.out.lock();
+        let result = match self.format {
+            Format::Text => writeln!(
+                out,
+                "Cpu usage: {:.2} System usage: {:.2} Tot usage: {:.2} {:.2}{} \
+                 Fairness: {:.3} Thread {}: {:.2}-{:.2} {:.2?} \
+                 Thread cpu (user/sys): {} running: {} sleeping: {} \
+                 Switches/s (vol/invol): {:.0}/{:.0}",
+                sample.cpu_usage,
+                sample.system_usage,
+                sample.total_usage(),
+                sample.ops_per_sec / (1024.0 * 1024.0),
+                run.unit.rate_label(),
+                sample.fairness(),
+                run.unit.rate_label(),
+                sample.min_thread_ops_per_sec() / (1024.0 * 1024.0),
+                sample.max_thread_ops_per_sec() / (1024.0 * 1024.0),
+                sample
+                    .thread_ops_per_sec
+                    .iter()
+                    .map(|ops| ops / (1024.0 * 1024.0))
+                    .collect::<Vec<_>>(),
+                format_thread_cpu(&sample.thread_cpu),
+                sample.threads_running(),
+                sample.threads_sleeping(),
+                sample.voluntary_switches_per_sec(),
+                sample.involuntary_switches_per_sec()
+            )
+            .and_then(|_| {
+                sample
+                    .metrics
+                    .iter()
+                    .try_for_each(|m| writeln!(out, "  {}: {:.2}", metric_label(m), m.value))
+            }),
+            _ => self.write_record(
+                &mut *out,
+                &Record {
+                    kind: "sample",
+                    stat: None,
+                    timestamp: unix_timestamp(),
+                    benchmark: &run.benchmark,
+                    threads: run.threads,
+                    cpu_user: sample.cpu_usage,
+                    cpu_system: sample.system_usage,
+                    ops_per_sec: sample.ops_per_sec,
+                    unit: run.unit,
+                    worker_cpus: run.worker_cpus.as_deref(),
+                    thread_ops_per_sec: sample.thread_ops_per_sec.clone(),
+                    fairness: sample.fairness(),
+                    min_thread_ops_per_sec: sample.min_thread_ops_per_sec(),
+                    max_thread_ops_per_sec: sample.max_thread_ops_per_sec(),
+                    options: &run.options,
+                    cumulative_ops_per_sec: sample.cumulative_ops_per_sec,
+                    outliers: None,
+                    metrics: sample.metrics.clone(),
+                    thread_cpu_user: sample.thread_cpu.iter().map(|t| t.user).collect(),
+                    thread_cpu_system: sample.thread_cpu.iter().map(|t| t.system).collect(),
+                    threads_running: sample.threads_running() as f64,
+                    threads_sleeping: sample.threads_sleeping() as f64,
+                    voluntary_switches_per_sec: sample.voluntary_switches_per_sec(),
+                    involuntary_switches_per_sec: sample.involuntary_switches_per_sec(),
+                },
+            ),
+        };
+        result.and_then(|_| out.flush())
+    }
+
+    pub fn summary(&self, run: &RunInfo, samples: &[Sample]) -> std::io::Result<()> {
+        let summarize = |f: &dyn Fn(&Sample) -> f64| {
+            Summary::from_samples(&samples.iter().map(f).collect::<Vec<_>>())
+        };
+        let ops = summarize(&|s| s.ops_per_sec);
+        let cpu = summarize(&|s| s.cpu_usage);
+        let system = summarize(&|s| s.system_usage);
+        let fairness = summarize(&|s| s.fairness());
+        let running = summarize(&|s| s.threads_running() as f64);
+        let sleeping = summarize(&|s| s.threads_sleeping() as f64);
+        let voluntary = summarize(&|s| s.voluntary_switches_per_sec());
+        let involuntary = summarize(&|s| s.involuntary_switches_per_sec());
+        let outliers = stats::outliers(&samples.iter().map(|s| s.ops_per_sec).collect::<Vec<_>>());
+        let cumulative = samples.last().map_or(0.0, |s| s.cumulative_ops_per_sec);
+        let metrics: Vec<_> = samples
+            .last()
+            .map_or(&[][..], |s| &s.metrics)
+            .iter()
+            .enumerate()
+            .map(|(i, metric)| {
+                let value = |s: &Sample| s.metrics.get(i).map_or(0.0, |m| m.value);
+                (metric, summarize(&value))
+            })
+            .collect();
+
+        let mut out = self.out.lock();
+        let result = match self.format {
+            Format::Text => {
+                let total = summarize(&|s| s.total_usage());
+                let mops = Summary::from_samples(
+                    &samples
+                        .iter()
+                        .map(|s| s.ops_per_sec / (1024.0 * 1024.0))
+                        .collect::<Vec<_>>(),
+                );
+                let pinning = match &run.worker_cpus {
+                    Some(cpus) => format!(" (workers pinned to cpus {:?})", cpus),
+                    None => String::new(),
+                };
+                writeln!(out, "Summary over {} samples{}:", samples.len(), pinning)
+                    .and_then(|_| write_text_summary(&mut *out, run.unit.rate_label(), &mops))
+                    .and_then(|_| write_text_summary(&mut *out, "Cpu usage", &cpu))
+                    .and_then(|_| write_text_summary(&mut *out, "System usage", &system))
+                    .and_then(|_| write_text_summary(&mut *out, "Tot usage", &total))
+                    .and_then(|_| write_text_summary(&mut *out, "Fairness", &fairness))
+                    .and_then(|_| write_text_summary(&mut *out, "Running", &running))
+                    .and_then(|_| write_text_summary(&mut *out, "Sleeping", &sleeping))
+                    .and_then(|_| write_text_summary(&mut *out, "Vol switches", &voluntary))
+                    .and_then(|_| write_text_summary(&mut *out, "Invol switches", &involuntary))
+                    .and_then(|_| {
+                        metrics.iter().try_for_each(|(metric, summary)| {
+                            write_text_summary(&mut *out, &metric_label(metric), summary)
+                        })
+                    })
+                    .and_then(|_| {
+                        writeln!(
+                            out,
+                            "  {:<14} {:.2}{}",
+                            "Overall",
+                            cumulative / (1024.0 * 1024.0),
+                            run.unit.rate_label()
+                        )
+                    })
+                    .and_then(|_| {
+                        if !run.memory_bandwidth {
+                            return Ok(());
+                        }
+                        // Bandwidths are usually quoted in decimal gigabytes, as by STREAM
+                        let per_thread = (0..run.threads)
+                            .map(|t| {
+                                let rate = summarize(&|s| {
+                                    s.thread_ops_per_sec.get(t).copied().unwrap_or(0.0)
+                                });
+                                format!("{:.2}", rate.mean / 1e9)
+                            })
+                            .collect::<Vec<_>>();
+                        writeln!(
+                            out,
+                            "  {:<14} {:.2}GB/s per thread: [{}]",
+                            "Bandwidth",
+                            cumulative / 1e9,
+                            per_thread.join(", ")
+                        )
+                    })
+                    .and_then(|_| {
+                        if outliers.is_empty() {
+                            return Ok(());
+                        }
+                        writeln!(
+                            out,
+                            "  {:<14} {} of {} samples {:?}",
+                            "Outliers",
+                            outliers.len(),
+                            samples.len(),
+                            outliers
+                        )
+                    })
+            }
+            _ => {
+                let timestamp = unix_timestamp();
+                let (ops, cpu, system) = (ops.values(), cpu.values(), system.values());
+                let fairness = fairness.values();
+                let (running, sleeping) = (running.values(), sleeping.values());
+                let (voluntary, involuntary) = (voluntary.values(), involuntary.values());
+                let min_thread = summarize(&|s| s.min_thread_ops_per_sec()).values();
+                let max_thread = summarize(&|s| s.max_thread_ops_per_sec()).values();
+                let threads_count = samples.iter().map(|s| s.thread_ops_per_sec.len()).max();
+                let per_thread: Vec<_> = (0..threads_count.unwrap_or(0))
+                    .map(|t| {
+                        summarize(&|s| s.thread_ops_per_sec.get(t).copied().unwrap_or(0.0)).values()
+                    })
+                    .collect();
+                let per_thread_cpu = |f: fn(&ThreadCpu) -> f64| -> Vec<_> {
+                    (0..threads_count.unwrap_or(0))
+                        .map(|t| summarize(&|s| s.thread_cpu.get(t).map_or(0.0, f)).values())
+                        .collect()
+                };
+                let thread_user = per_thread_cpu(|t| t.user);
+                let thread_system = per_thread_cpu(|t| t.system);
+                (0..Summary::STAT_NAMES.len()).try_for_each(|i| {
+                    self.write_record(
+                        &mut *out,
+                        &Record {
+                            kind: "summary",
+                            stat: Some(Summary::STAT_NAMES[i]),
+                            timestamp,
+                            benchmark: &run.benchmark,
+                            threads: run.threads,
+                            cpu_user: cpu[i],
+                            cpu_system: system[i],
+                            ops_per_sec: ops[i],
+                            unit: run.unit,
+                            worker_cpus: run.worker_cpus.as_deref(),
+                            thread_ops_per_sec: per_thread.iter().map(|t| t[i]).collect(),
+                            fairness: fairness[i],
+                            min_thread_ops_per_sec: min_thread[i],
+                            max_thread_ops_per_sec: max_thread[i],
+                            options: &run.options,
+                            cumulative_ops_per_sec: cumulative,
+                            outliers: Some(&outliers),
+                            metrics: metrics
+                                .iter()
+                                .map(|(metric, summary)| MetricSample {
+                                    value: summary.values()[i],
+                                    ..(*metric).clone()
+                                })
+                                .collect(),
+                            thread_cpu_user: thread_user.iter().map(|t| t[i]).collect(),
+                            thread_cpu_system: thread_system.iter().map(|t| t[i]).collect(),
+                            threads_running: running[i],
+                            threads_sleeping: sleeping[i],
+                            voluntary_switches_per_sec: voluntary[i],
+                            involuntary_switches_per_sec: involuntary[i],
+                        },
+                    )
+                })
+            }
+        };
+        result.and_then(|_| out.flush())
+    }
+
+    /// Reports the latency percentiles recorded by the workers of a run.
+    pub fn latency(&self, run: &RunInfo, percentiles: &LatencyPercentiles) -> std::io::Result<()> {
+        let mut out = self.out.lock();
+        let names = LatencyPercentiles::STAT_NAMES;
+        let values = percentiles.values();
+        let result = match self.format {
+            Format::Text => {
+                let line = names
+                    .iter()
+                    .zip(values)
+                    .map(|(name, ns)| format!("{}: {}", name, format_nanos(ns)))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                writeln!(out, "  {:<14} {}", "Latency", line)
+            }
+            _ => {
+                let timestamp = unix_timestamp();
+                names.iter().zip(values).try_for_each(|(stat, latency_ns)| {
+                    let record = LatencyRecord {
+                        kind: "latency",
+                        stat,
+                        timestamp,
+                        benchmark: &run.benchmark,
+                        threads: run.threads,
+                        latency_ns,
+                        options: &run.options,
+                    };
+                    match self.format {
+                        Format::Json => {
+                            serde_json::to_writer(&mut *out, &record)?;
+                            writeln!(out)
+                        }
+                        _ => record.write_csv(&mut *out),
+                    }
+                })
+            }
+        };
+        result.and_then(|_| out.flush())
+    }
+
+    /// Reports the system the benchmarks run on, before any of them.
+    pub fn fingerprint(&self, fingerprint: &Fingerprint) -> std::io::Result<()> {
+        let mut out = self.out.lock();
+        let record = FingerprintRecord {
+            kind: "fingerprint",
+            timestamp: unix_timestamp(),
+            fingerprint,
+        };
+        let result = match self.format {
+            Format::Text => {
+                let optional = |value: &Option<String>| value.clone().unwrap_or("-".to_string());
+                let caches = fingerprint
+                    .caches
+                    .iter()
+                    .map(|c| format!("{} {} ({} cpus)", c.name(), c.size, c.shared_by))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                writeln!(
+                    out,
+                    "System: {}, {} cpus ({} allowed), {} cores, {} packages, {} threads per core\n  \
+                     Caches: {}\n  Kernel: {} Governor: {} THP: {} Cgroup cpu limit: {}",
+                    fingerprint.cpu_model,
+                    fingerprint.cpus,
+                    fingerprint.allowed_cpus,
+                    fingerprint.cores,
+                    fingerprint.packages,
+                    fingerprint.threads_per_core,
+                    caches,
+                    fingerprint.kernel,
+                    optional(&fingerprint.governor),
+                    optional(&fingerprint.transparent_hugepages),
+                    optional(&fingerprint.cgroup_cpu_limit.map(|cpus| format!("{:.2}", cpus)))
+                )
+            }
+            Format::Json => serde_json::to_writer(&mut *out, &record)
+                .map_err(std::io::Error::from)
+                .and_then(|_| writeln!(out)),
+            Format::Csv => record.write_csv(&mut *out),
+        };
+        result.and_then(|_| out.flush())
+    }
+
+    /// Reports a phase run by the benchmark after its workers exited.
+    pub fn phase(&self, run: &RunInfo, phase: &Phase) -> std::io::Result<()> {
+        let mut out = self.out.lock();
+        let record = PhaseRecord {
+            kind: "phase",
+            stat: phase.name,
+            timestamp: unix_timestamp(),
+            benchmark: &run.benchmark,
+            threads: run.threads,
+            ops_per_sec: phase.per_sec(),
+            unit: phase.unit,
+            amount: phase.amount,
+            elapsed_secs: phase.elapsed.as_secs_f64(),
+            options: &run.options,
+        };
+        let result = match self.format {
+            Format::Text => writeln!(
+                out,
+                "  {:<14} {:.2}{} ({} in {:.2?})",
+                format!("{} phase", phase.name),
+                record.ops_per_sec / (1024.0 * 1024.0),
+                phase.unit.rate_label(),
+                phase.amount,
+                phase.elapsed
+            ),
+            Format::Json => serde_json::to_writer(&mut *out, &record)
+                .map_err(std::io::Error::from)
+                .and_then(|_| writeln!(out)),
+            Format::Csv => record.write_csv(&mut *out),
+        };
+        result.and_then(|_| out.flush())
+    }
+
+    /// Prints the thread scaling table of a sweep.
+    pub fn scaling_table(
+        &self,
+        benchmark: &str,
+        unit: Unit,
+        rows: &[ScalingRow],
+    ) -> std::io::Result<()> {
+        let mut table = format!(
+            "Scaling of {}:\n  {:>8} {:>14} {:>14} {:>10}\n",
+            benchmark,
+            "threads",
+            unit.rate_label(),
+            format!("{}/thread", unit.rate_label()),
+            "efficiency"
+        );
+        for row in rows {
+            table += &format!(
+                "  {:>8} {:>14.2} {:>14.2} {:>9.1}%\n",
+                row.threads,
+                row.ops_per_sec / (1024.0 * 1024.0),
+                row.ops_per_thread / (1024.0 * 1024.0),
+                row.efficiency * 100.0
+            );
+        }
+        self.write_table(&table)
+    }
+
+    /// Prints the throughput of a lock benchmark against the swept critical section or think time.
+    pub fn work_table(
+        &self,
+        benchmark: &str,
+        target: WorkTarget,
+        rows: &[WorkRow],
+    ) -> std::io::Result<()> {
+        let mut table = format!(
+            "{} sweep of {}:\n  {:>10} {:>14} {:>14} {:>16}\n",
+            target.name(),
+            benchmark,
+            "work",
+            "M/s",
+            "M/s/thread",
+            "vs lock-free"
+        );
+        for row in rows {
+            let efficiency = match row.lock_free_efficiency {
+                Some(efficiency) => format!("{:.1}%", efficiency * 100.0),
+                None => "-".to_string(),
+            };
+            table += &format!(
+                "  {:>10} {:>14.2} {:>14.2} {:>16}\n",
+                row.work.to_string(),
+                row.ops_per_sec / (1024.0 * 1024.0),
+                row.ops_per_thread / (1024.0 * 1024.0),
+                efficiency
+            );
+        }
+        self.write_table(&table)
+    }
+
+    /// Prints the throughput of a strided benchmark against the distance between the per-thread values.
+    pub fn stride_table(&self, benchmark: &str, rows: &[StrideRow]) -> std::io::Result<()> {
+        let mut table = format!(
+            "Stride sweep of {}:\n  {:>8} {:>14} {:>14} {:>10}\n",
+            benchmark, "stride", "M/s", "M/s/thread", "vs best"
+        );
+        for row in rows {
+            table += &format!(
+                "  {:>8} {:>14.2} {:>14.2} {:>9.1}%\n",
+                row.stride,
+                row.ops_per_sec / (1024.0 * 1024.0),
+                row.ops_per_thread / (1024.0 * 1024.0),
+                row.relative * 100.0
+            );
+        }
+        if let Some(stride) = false_sharing_granularity(rows) {
+            table += &format!("  No false sharing from {} bytes apart\n", stride);
+        }
+        self.write_table(&table)
+    }
+
+    /// Prints the latency of a load against the working set of the pointer chase,
+    /// marking the steps to the next cache level or to memory.
+    pub fn working_set_table(
+        &self,
+        benchmark: &str,
+        rows: &[WorkingSetRow],
+    ) -> std::io::Result<()> {
+        let mut table = format!(
+            "Working set sweep of {}:\n  {:>10} {:>14} {:>12} {:>10}\n",
+            benchmark, "size", "M loads/s", "ns/load", "vs prev"
+        );
+        for row in rows {
+            table += &format!(
+                "  {:>10} {:>14.2} {:>12.2} {:>9.2}x{}\n",
+                format_size(row.size),
+                row.loads_per_sec / (1024.0 * 1024.0),
+                row.nanos_per_load,
+                row.step,
+                if row.is_level_step() {
+                    "  <- level"
+                } else {
+                    ""
+                }
+            );
+        }
+        self.write_table(&table)
+    }
+
+    /// Prints how the throughput of a benchmark degrades as its threads outnumber the cpus.
+    pub fn oversubscription_table(
+        &self,
+        benchmark: &str,
+        unit: Unit,
+        rows: &[OversubscriptionRow],
+    ) -> std::io::Result<()> {
+        let mut table = format!(
+            "Oversubscription of {}:\n  {:>6} {:>8} {:>14} {:>10} {:>16} {:>17}\n",
+            benchmark,
+            "factor",
+            "threads",
+            unit.rate_label(),
+            "vs 1x",
+            "invol switches/s",
+            "preempted holds/s"
+        );
+        for row in rows {
+            let result = &row.result;
+            table += &format!(
+                "  {:>5}x {:>8} {:>14.2} {:>9.1}% {:>16.0} {:>17}\n",
+                result.factor,
+                result.threads,
+                result.ops_per_sec / (1024.0 * 1024.0),
+                row.relative * 100.0,
+                result.involuntary_switches_per_sec,
+                match result.preempted_holds_per_sec {
+                    Some(holds) => format!("{:.0}", holds),
+                    None => "-".to_string(),
+                }
+            );
+        }
+        self.write_table(&table)
+    }
+
+    /// Prints the comparison of the runs against a saved baseline.
+    pub fn comparison_table(&self, baseline: &str, rows: &[Comparison]) -> std::io::Result<()> {
+        let mut table = format!(
+            "Comparison with baseline {}:\n  {:<36} {:>8} {:>14} {:>14} {:>9} {:>8}  {}\n",
+            baseline, "benchmark", "threads", "baseline", "current", "change", "p-value", "verdict"
+        );
+        let optional = |value: Option<f64>, f: &dyn Fn(f64) -> String| match value {
+            Some(value) => f(value),
+            None => "-".to_string(),
+        };
+        for row in rows {
+            let rate =
+                |ops: f64| format!("{:.2}{}", ops / (1024.0 * 1024.0), row.unit.rate_label());
+            table += &format!(
+                "  {:<36} {:>8} {:>14} {:>14} {:>9} {:>8}  {}\n",
+                row.benchmark,
+                row.threads,
+                optional(row.baseline, &rate),
+                rate(row.current),
+                optional(row.change, &|change| format!("{:+.1}%", change * 100.0)),
+                optional(row.p_value, &|p| format!("{:.3}", p)),
+                row.verdict.name()
+            );
+        }
+        self.write_table(&table)
+    }
+
+    /// Reports the results of all the runs of a suite, the repetitions of an entry merged.
+    /// The machine-readable formats get a summary record per run, besides the table on stderr.
+    pub fn suite_table(&self, suite: &str, results: &[RunResult]) -> std::io::Result<()> {
+        let mut table = format!(
+            "Suite {}:\n  {:<36} {:>8} {:>8} {:>16} {:>8}\n",
+            suite, "benchmark", "threads", "samples", "mean", "cv"
+        );
+        for result in results {
+            let summary = Summary::from_samples(&result.ops_per_sec);
+            table += &format!(
+                "  {:<36} {:>8} {:>8} {:>16} {:>7.1}%\n",
+                result.benchmark,
+                result.threads,
+                result.ops_per_sec.len(),
+                format!(
+                    "{:.2}{}",
+                    summary.mean / (1024.0 * 1024.0),
+                    result.unit.rate_label()
+                ),
+                summary.stddev / summary.mean.max(f64::MIN_POSITIVE) * 100.0
+            );
+        }
+        self.write_table(&table)?;
+        if self.format == Format::Text {
+            return Ok(());
+        }
+
+        let mut out = self.out.lock();
+        let timestamp = unix_timestamp();
+        let result = results.iter().try_for_each(|result| {
+            let values = Summary::from_samples(&result.ops_per_sec).values();
+            Summary::STAT_NAMES
+                .iter()
+                .zip(values)
+                .try_for_each(|(stat, ops_per_sec)| {
+                    let record = SuiteRecord {
+                        kind: "suite",
+                        stat,
+                        timestamp,
+                        suite,
+                        benchmark: &result.benchmark,
+                        threads: result.threads,
+                        ops_per_sec,
+                        unit: result.unit,
+                        samples: result.ops_per_sec.len(),
+                        options: &result.options,
+                    };
+                    match self.format {
+                        Format::Json => serde_json::to_writer(&mut *out, &record)
+                            .map_err(std::io::Error::from)
+                            .and_then(|_| writeln!(out)),
+                        _ => record.write_csv(&mut *out),
+                    }
+                })
+        });
+        result.and_then(|_| out.flush())
+    }
+
+    /// Machine-readable formats already carry a summary per run, so the
+    /// tables only go to stderr for the user.
+    fn write_table(&self, table: &str) -> std::io::Result<()> {
+        if self.format == Format::Text {
+            let mut out = self.out.lock();
+            out.write_all(table.as_bytes()).and_then(|_| out.flush())
+        } else {
+            let mut err = std::io::stderr().lock();
+            err.write_all(table.as_bytes()).and_then(|_| err.flush())
+        }
+    }
+
+    fn write_record(&self, out: &mut dyn Write, record: &Record) -> std::io::Result<()> {
+        match self.format {
+            Format::Json => {
+                serde_json::to_writer(&mut *out, record)?;
+                writeln!(out)
+            }
+            _ => record.write_csv(out),
+        }
+    }
+}
+
+fn write_text_summary(out: &mut dyn Write, label: &str, summary: &Summary) -> std::io::Result<()> {
+    writeln!(
+        out,
+        "  {:<14} mean: {:.2} median: {:.2} min: {:.2} max: {:.2} stddev: {:.2} mad: {:.2}",
+        label, summary.mean, summary.median, summary.min, summary.max, summary.stddev, summary.mad
+    )
+}
+
+fn metric_label(metric: &MetricSample) -> String {
+    match metric.kind {
+        MetricKind::Counter => format!("{}/s", metric.name),
+        MetricKind::Gauge => metric.name.clone(),
+    }
+}
+
+fn format_nanos(nanos: f64) -> String {
+    if nanos < 1e3 {
+        format!("{:.0}ns", nanos)
+    } else if nanos < 1e6 {
+        format!("{:.2}us", nanos / 1e3)
+    } else {
+        format!("{:.2}ms", nanos / 1e6)
+    }
+}
+
+/// Formats a size in bytes with the largest binary suffix dividing it, as accepted on the command line.
+fn format_size(bytes: u64) -> String {
+    let units = [("G", 1 << 30), ("M", 1 << 20), ("K", 1 << 10)];
+    match units.iter().find(|(_, scale)| bytes.is_multiple_of(*scale)) {
+        Some((suffix, scale)) => format!("{}{}", bytes / scale, suffix),
+        None => bytes.to_string(),
+    }
+}
+
+/// Formats the cpu time of each worker as `user/system` fractions, with its scheduler state.
+fn format_thread_cpu(threads: &[ThreadCpu]) -> String {
+    let threads = threads
+        .iter()
+        .map(|t| format!("{:.2}/{:.2} {}", t.user, t.system, t.state))
+        .collect::<Vec<_>>();
+    format!("[{}]", threads.join(", "))
+}
+
+fn csv_options(options: &BenchOptions) -> std::io::Result<String> {
+    csv_fields(options)
+}
+
+/// Joins the fields of a struct as `key=value` pairs in a single quoted column, compound values
+/// being written as json.
+fn csv_fields(value: &impl Serialize) -> std::io::Result<String> {
+    let fields = match serde_json::to_value(value)? {
+        serde_json::Value::Object(fields) => fields
+            .iter()
+            .map(|(key, value)| match value {
+                serde_json::Value::String(value) => format!("{}={}", key, value),
+                value => format!("{}={}", key, value),
+            })
+            .collect::<Vec<_>>()
+            .join(";"),
+        value => value.to_string(),
+    };
+    Ok(csv_escape(&fields))
+}
+
+/// Quotes a field holding a separator, a quote or a line break, doubling its quotes (RFC 4180).
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn join_csv_list<T: ToString>(values: &[T]) -> String {
+    values
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+fn unix_timestamp() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs_f64()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::report::csv_escape;
+
+    #[test]
+    fn escape() {
+        assert_eq!(csv_escape("atomic/inc"), "atomic/inc");
+        assert_eq!(csv_escape("dir=/a,b"), "\"dir=/a,b\"");
+        assert_eq!(csv_escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
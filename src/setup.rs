@@ -0,0 +1,76 @@
+use parking_lot::{Condvar, Mutex};
+use std::sync::Arc;
+
+/// Lets the tracker wait for the workers to start and finish their setup, such as allocating
+/// and filling their buffers, before the warmup starts.
+#[derive(Clone, Default)]
+pub struct SetupLatch(Arc<(Mutex<SetupState>, Condvar)>);
+
+#[derive(Default)]
+struct SetupState {
+    pending: usize,
+    /// No more setup can begin, all the workers having been spawned
+    sealed: bool,
+}
+
+/// A setup in progress, done when dropped, including when the setup panics.
+pub struct PendingSetup(SetupLatch);
+
+impl SetupLatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn begin(&self) -> PendingSetup {
+        let mut state = self.0 .0.lock();
+        assert!(!state.sealed, "Setup begun after the workers were spawned");
+        state.pending += 1;
+        PendingSetup(self.clone())
+    }
+
+    pub fn seal(&self) {
+        self.0 .0.lock().sealed = true;
+        self.0 .1.notify_all();
+    }
+
+    /// Blocks until the latch is sealed and all the setups begun are done.
+    pub fn wait(&self) {
+        let mut state = self.0 .0.lock();
+        while !state.sealed || state.pending > 0 {
+            self.0 .1.wait(&mut state);
+        }
+    }
+}
+
+impl Drop for PendingSetup {
+    fn drop(&mut self) {
+        let latch = &self.0 .0;
+        latch.0.lock().pending -= 1;
+        latch.1.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::setup::SetupLatch;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn wait() {
+        let latch = SetupLatch::new();
+        let done = Arc::new(AtomicBool::new(false));
+        let pending = latch.begin();
+        let worker_done = done.clone();
+        let worker = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            worker_done.store(true, Ordering::Relaxed);
+            drop(pending);
+        });
+        latch.seal();
+        latch.wait();
+        assert!(done.load(Ordering::Relaxed));
+        worker.join().unwrap();
+    }
+}
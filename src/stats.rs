@@ -0,0 +1,272 @@
+use hdrhistogram::Histogram;
+
+/// Descriptive statistics over a series of per-interval samples.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Summary {
+    pub mean: f64,
+    pub median: f64,
+    pub min: f64,
+    pub max: f64,
+    pub stddev: f64,
+    /// Median absolute deviation from the median
+    pub mad: f64,
+}
+
+impl Summary {
+    pub const STAT_NAMES: [&'static str; 6] = ["mean", "median", "min", "max", "stddev", "mad"];
+
+    /// The statistics in the same order as `STAT_NAMES`.
+    pub fn values(&self) -> [f64; 6] {
+        [
+            self.mean,
+            self.median,
+            self.min,
+            self.max,
+            self.stddev,
+            self.mad,
+        ]
+    }
+
+    pub fn from_samples(samples: &[f64]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+
+        let count = sorted.len() as f64;
+        let mean = sorted.iter().sum::<f64>() / count;
+        let variance = if sorted.len() > 1 {
+            sorted.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (count - 1.0)
+        } else {
+            0.0
+        };
+
+        let median = median_of_sorted(&sorted);
+        Self {
+            mean,
+            median,
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            stddev: variance.sqrt(),
+            mad: median_absolute_deviation(&sorted, median),
+        }
+    }
+}
+
+/// Modified z-score beyond which a sample is an outlier (Iglewicz and Hoaglin).
+const OUTLIER_SCORE: f64 = 3.5;
+
+/// Indices of the samples far from the median relative to the median absolute deviation,
+/// falling back to the mean absolute deviation when more than half the samples are equal.
+pub fn outliers(samples: &[f64]) -> Vec<usize> {
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    if sorted.len() < 3 {
+        return vec![];
+    }
+    let median = median_of_sorted(&sorted);
+    let mad = median_absolute_deviation(&sorted, median);
+    let scale = if mad > 0.0 {
+        mad / 0.6745
+    } else {
+        let mean_deviation =
+            samples.iter().map(|x| (x - median).abs()).sum::<f64>() / samples.len() as f64;
+        mean_deviation * 1.253314
+    };
+    if scale == 0.0 {
+        return vec![];
+    }
+    samples
+        .iter()
+        .enumerate()
+        .filter(|(_, x)| (*x - median).abs() / scale > OUTLIER_SCORE)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+fn median_absolute_deviation(sorted: &[f64], median: f64) -> f64 {
+    let mut deviations: Vec<_> = sorted.iter().map(|x| (x - median).abs()).collect();
+    deviations.sort_by(|a, b| a.total_cmp(b));
+    median_of_sorted(&deviations)
+}
+
+/// Percentiles of a latency distribution, in nanoseconds.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LatencyPercentiles {
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub p999: f64,
+    pub max: f64,
+}
+
+impl LatencyPercentiles {
+    pub const STAT_NAMES: [&'static str; 5] = ["p50", "p90", "p99", "p99.9", "max"];
+
+    /// The percentiles in the same order as `STAT_NAMES`.
+    pub fn values(&self) -> [f64; 5] {
+        [self.p50, self.p90, self.p99, self.p999, self.max]
+    }
+
+    /// Reads the percentiles out of a histogram of nanoseconds, `None` if it is empty.
+    pub fn from_histogram(histogram: &Histogram<u64>) -> Option<Self> {
+        if histogram.is_empty() {
+            return None;
+        }
+        let percentile = |q: f64| histogram.value_at_quantile(q) as f64;
+        Some(Self {
+            p50: percentile(0.5),
+            p90: percentile(0.9),
+            p99: percentile(0.99),
+            p999: percentile(0.999),
+            max: histogram.max() as f64,
+        })
+    }
+}
+
+/// Jain's fairness index: 1 when all the values are equal, down to 1/n when a single one is non-zero.
+pub fn jain_fairness(values: &[f64]) -> f64 {
+    let sum = values.iter().sum::<f64>();
+    let sum_squares = values.iter().map(|x| x * x).sum::<f64>();
+    if sum_squares == 0.0 {
+        return 1.0;
+    }
+    sum * sum / (values.len() as f64 * sum_squares)
+}
+
+/// Two-sided p-value of the Mann-Whitney U test that both series come from the same
+/// distribution, using the normal approximation with a tie correction.
+pub fn mann_whitney_p_value(a: &[f64], b: &[f64]) -> f64 {
+    let (n1, n2) = (a.len() as f64, b.len() as f64);
+    if a.is_empty() || b.is_empty() {
+        return 1.0;
+    }
+
+    let mut values: Vec<_> = a
+        .iter()
+        .map(|&x| (x, true))
+        .chain(b.iter().map(|&x| (x, false)))
+        .collect();
+    values.sort_by(|x, y| x.0.total_cmp(&y.0));
+
+    // Ranks start at 1, tied values share the mean of their ranks
+    let mut rank_sum_a = 0.0;
+    let mut tie_correction = 0.0;
+    let mut start = 0;
+    while start < values.len() {
+        let end = start
+            + values[start..]
+                .iter()
+                .take_while(|(x, _)| *x == values[start].0)
+                .count();
+        let rank = (start + end + 1) as f64 / 2.0;
+        rank_sum_a += rank * values[start..end].iter().filter(|(_, in_a)| *in_a).count() as f64;
+        let ties = (end - start) as f64;
+        tie_correction += ties.powi(3) - ties;
+        start = end;
+    }
+
+    let u = rank_sum_a - n1 * (n1 + 1.0) / 2.0;
+    let n = n1 + n2;
+    let variance = n1 * n2 / 12.0 * ((n + 1.0) - tie_correction / (n * (n - 1.0)));
+    if variance <= 0.0 {
+        return 1.0;
+    }
+    let z = ((u - n1 * n2 / 2.0).abs() - 0.5).max(0.0) / variance.sqrt();
+    (2.0 * (1.0 - normal_cdf(z))).min(1.0)
+}
+
+/// Standard normal cumulative distribution, from the Abramowitz and Stegun approximation of erf.
+fn normal_cdf(z: f64) -> f64 {
+    let x = z.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let poly = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let erf = 1.0 - poly * (-x * x).exp();
+    if z >= 0.0 {
+        (1.0 + erf) / 2.0
+    } else {
+        (1.0 - erf) / 2.0
+    }
+}
+
+fn median_of_sorted(sorted: &[f64]) -> f64 {
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::stats::{
+        jain_fairness, mann_whitney_p_value, outliers, LatencyPercentiles, Summary,
+    };
+    use hdrhistogram::Histogram;
+
+    #[test]
+    fn summary() {
+        let summary = Summary::from_samples(&[4.0, 1.0, 3.0, 2.0]);
+        assert_eq!(summary.mean, 2.5);
+        assert_eq!(summary.median, 2.5);
+        assert_eq!(summary.min, 1.0);
+        assert_eq!(summary.max, 4.0);
+        assert!((summary.stddev - 1.2909944).abs() < 1e-6);
+        assert_eq!(summary.mad, 1.0);
+
+        let single = Summary::from_samples(&[7.0]);
+        assert_eq!(single.median, 7.0);
+        assert_eq!(single.stddev, 0.0);
+    }
+
+    #[test]
+    fn outlier_detection() {
+        let samples = [10.0, 10.2, 9.9, 10.1, 3.0, 10.0, 9.8, 10.3];
+        assert_eq!(outliers(&samples), vec![4]);
+        assert_eq!(outliers(&[5.0, 5.0, 5.0, 5.0, 9.0]), vec![4]);
+        assert!(outliers(&[5.0; 6]).is_empty());
+        assert!(outliers(&[1.0, 2.0, 3.0, 4.0, 5.0]).is_empty());
+    }
+
+    #[test]
+    fn fairness() {
+        assert_eq!(jain_fairness(&[5.0, 5.0, 5.0, 5.0]), 1.0);
+        assert_eq!(jain_fairness(&[8.0, 0.0, 0.0, 0.0]), 0.25);
+        assert!((jain_fairness(&[1.0, 3.0]) - 0.8).abs() < 1e-9);
+    }
+
+    #[test]
+    fn histogram_percentiles() {
+        let mut histogram = Histogram::<u64>::new(3).unwrap();
+        assert!(LatencyPercentiles::from_histogram(&histogram).is_none());
+        for value in 1..=1000 {
+            histogram.record(value).unwrap();
+        }
+        histogram.record_n(5000, 10).unwrap();
+        let percentiles = LatencyPercentiles::from_histogram(&histogram).unwrap();
+        assert_eq!(percentiles.p50, 505.0);
+        assert_eq!(percentiles.p99, 1000.0);
+        // Values past 2048 share buckets of 4 at 3 significant digits
+        assert!((percentiles.p999 - 5000.0).abs() < 4.0);
+        assert!((percentiles.max - 5000.0).abs() < 4.0);
+    }
+
+    #[test]
+    fn mann_whitney() {
+        let a = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0];
+        let b = [9.0, 10.0, 11.0, 12.0, 13.0, 14.0, 15.0, 16.0];
+        assert!(mann_whitney_p_value(&a, &b) < 0.001);
+        assert!(mann_whitney_p_value(&a, &a) > 0.9);
+        assert_eq!(mann_whitney_p_value(&[3.0; 4], &[3.0; 4]), 1.0);
+
+        // U = 3, so z = (12.5 - 3 - 0.5) / sqrt(25 * 11 / 12) = 1.88
+        let c = [1.1, 2.3, 3.2, 4.8, 5.5];
+        let d = [3.9, 5.1, 6.4, 7.7, 8.2];
+        assert!((mann_whitney_p_value(&c, &d) - 0.0601).abs() < 0.001);
+    }
+}
@@ -0,0 +1,236 @@
+use std::path::Path;
+use std::time::Duration;
+
+/// Options applying to the whole suite, only accepted on the command line, all taking a value but `list`.
+const SUITE_WIDE: &[&str] = &[
+    "list",
+    "format",
+    "output",
+    "save-baseline",
+    "compare",
+    "baseline-dir",
+    "regression-threshold",
+    "suite",
+];
+
+/// Benchmarks run one after the other, read from a toml file such as `suites/example.toml`:
+#[doc = concat!("```toml\n", include_str!("../suites/example.toml"), "```")]
+///
+/// Besides `name` and `repetitions`, the keys of an entry are the long command line options,
+/// with true booleans as flags and arrays as comma separated lists.
+#[derive(Debug)]
+pub struct Suite {
+    pub cooldown: Duration,
+    pub entries: Vec<SuiteEntry>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SuiteEntry {
+    /// Benchmark name or glob pattern
+    pub name: String,
+    pub repetitions: usize,
+    /// Command line arguments of the entry, without the program name
+    pub args: Vec<String>,
+}
+
+impl Suite {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| format!("Cannot read suite {}: {}", path.display(), err))?;
+        Self::parse(&content).map_err(|err| format!("Invalid suite {}: {}", path.display(), err))
+    }
+
+    fn parse(content: &str) -> Result<Self, String> {
+        let mut table: toml::Table = content.parse().map_err(|err| format!("{}", err))?;
+
+        let cooldown = match table.remove("cooldown") {
+            None => Duration::ZERO,
+            Some(value) => match value.as_float().or(value.as_integer().map(|i| i as f64)) {
+                Some(secs) if secs >= 0.0 && secs.is_finite() => Duration::from_secs_f64(secs),
+                _ => return Err(format!("Invalid cooldown {}", value)),
+            },
+        };
+        let entries = match table.remove("benchmark") {
+            Some(toml::Value::Array(entries)) => entries,
+            Some(_) => return Err("'benchmark' must be an array of tables".to_string()),
+            None => vec![],
+        };
+        if let Some(key) = table.keys().next() {
+            return Err(format!("Unknown key '{}'", key));
+        }
+        if entries.is_empty() {
+            return Err("No [[benchmark]] entry".to_string());
+        }
+
+        let entries = entries
+            .into_iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                match entry {
+                    toml::Value::Table(entry) => SuiteEntry::parse(entry),
+                    _ => Err("not a table".to_string()),
+                }
+                .map_err(|err| format!("benchmark entry {}: {}", i + 1, err))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { cooldown, entries })
+    }
+}
+
+impl SuiteEntry {
+    fn parse(mut entry: toml::Table) -> Result<Self, String> {
+        let name = match entry.remove("name") {
+            Some(toml::Value::String(name)) => name,
+            Some(value) => return Err(format!("Invalid name {}", value)),
+            None => return Err("Missing name".to_string()),
+        };
+        let repetitions = match entry.remove("repetitions") {
+            None => 1,
+            Some(value) => match value.as_integer() {
+                Some(repetitions) if repetitions > 0 => repetitions as usize,
+                _ => return Err(format!("Invalid repetitions {}", value)),
+            },
+        };
+
+        let mut args = vec![name.clone()];
+        for (key, value) in entry {
+            let option = key.replace('_', "-");
+            if SUITE_WIDE.contains(&option.as_str()) {
+                return Err(format!(
+                    "'{}' applies to the whole suite, give it on the command line",
+                    key
+                ));
+            }
+            let flag = format!("--{}", option);
+            match value {
+                toml::Value::Boolean(true) => args.push(flag),
+                toml::Value::Boolean(false) => {}
+                value => args.extend([flag, option_value(&key, &value)?]),
+            }
+        }
+        Ok(Self {
+            name,
+            repetitions,
+            args,
+        })
+    }
+}
+
+/// Checks that the command line running a suite, without the program name, only has suite-wide
+/// options, the benchmark options being given by the entries.
+pub fn check_command_line(args: impl IntoIterator<Item = String>) -> Result<(), String> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with('-') {
+            continue;
+        }
+        let (name, inline_value) = match arg.trim_start_matches('-').split_once('=') {
+            Some((name, _)) => (name, true),
+            None => (arg.trim_start_matches('-'), false),
+        };
+        if !arg.starts_with("--") || !SUITE_WIDE.contains(&name) {
+            return Err(format!(
+                "{} cannot be combined with --suite, give it in the suite entries",
+                arg
+            ));
+        }
+        if name != "list" && !inline_value {
+            args.next();
+        }
+    }
+    Ok(())
+}
+
+fn option_value(key: &str, value: &toml::Value) -> Result<String, String> {
+    match value {
+        toml::Value::String(s) => Ok(s.clone()),
+        toml::Value::Integer(i) => Ok(i.to_string()),
+        toml::Value::Float(f) => Ok(f.to_string()),
+        toml::Value::Array(values) => values
+            .iter()
+            .map(|value| match value {
+                toml::Value::Array(_) | toml::Value::Table(_) => Err(format!(
+                    "Invalid value of '{}': nested {}",
+                    key,
+                    value.type_str()
+                )),
+                value => option_value(key, value),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(|values| values.join(",")),
+        value => Err(format!("Invalid value of '{}': {}", key, value.type_str())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::suite::{check_command_line, Suite, SuiteEntry};
+    use std::time::Duration;
+
+    #[test]
+    fn parse() {
+        let suite = Suite::parse(
+            r#"
+            cooldown = 2.5
+
+            [[benchmark]]
+            name = "atomic/*"
+            sweep = [1, 2, 4]
+            duration = 3
+            repetitions = 2
+
+            [[benchmark]]
+            name = "writing/buckets"
+            fs_memory = "2G"
+            file-mode = "always-memory"
+            huge-pages = false
+            non-temporal = true
+            "#,
+        )
+        .unwrap();
+        assert_eq!(suite.cooldown, Duration::from_millis(2500));
+        assert_eq!(
+            suite.entries,
+            vec![
+                SuiteEntry {
+                    name: "atomic/*".to_string(),
+                    repetitions: 2,
+                    args: vec!["atomic/*", "--duration", "3", "--sweep", "1,2,4"]
+                        .into_iter()
+                        .map(String::from)
+                        .collect(),
+                },
+                SuiteEntry {
+                    name: "writing/buckets".to_string(),
+                    repetitions: 1,
+                    args: vec![
+                        "writing/buckets",
+                        "--file-mode",
+                        "always-memory",
+                        "--fs-memory",
+                        "2G",
+                        "--non-temporal",
+                    ]
+                    .into_iter()
+                    .map(String::from)
+                    .collect(),
+                },
+            ]
+        );
+
+        assert!(Suite::parse("cooldown = 1").is_err());
+        assert!(Suite::parse("[[benchmark]]\nduration = 1").is_err());
+        assert!(Suite::parse("[[benchmark]]\nname = \"a\"\nrepetitions = 0").is_err());
+        assert!(Suite::parse("[[benchmark]]\nname = \"a\"\noutput = \"x\"").is_err());
+        assert!(Suite::parse("[[benchmark]]\nname = \"a\"\nstride = { a = 1 }").is_err());
+    }
+
+    #[test]
+    fn command_line() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert!(check_command_line(args(&["--suite", "s.toml", "--format", "csv"])).is_ok());
+        assert!(check_command_line(args(&["--suite=s.toml", "--output", "-x", "--list"])).is_ok());
+        assert!(check_command_line(args(&["--suite", "s.toml", "-t", "4"])).is_err());
+        assert!(check_command_line(args(&["--suite", "s.toml", "--duration=3"])).is_err());
+    }
+}
@@ -0,0 +1,467 @@
+use crate::benchmarks::work::Work;
+use crate::benchmarks::{parse_lines_size, parse_stride, BenchOptions};
+use std::str::FromStr;
+
+/// Thread counts to run a benchmark with, one run per count.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ThreadSweep {
+    /// Powers of two up to the number of cpus, plus the number of cpus itself
+    Pow2,
+    List(Vec<usize>),
+}
+
+impl FromStr for ThreadSweep {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "pow2" {
+            return Ok(ThreadSweep::Pow2);
+        }
+        let counts = s
+            .split(',')
+            .map(|c| match c.trim().parse::<usize>() {
+                Ok(0) | Err(_) => Err(format!("Invalid thread count '{}'", c)),
+                Ok(count) => Ok(count),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ThreadSweep::List(counts))
+    }
+}
+
+impl ThreadSweep {
+    pub fn thread_counts(&self, cpu_count: usize) -> Vec<usize> {
+        match self {
+            ThreadSweep::Pow2 => {
+                let mut counts: Vec<_> = (0..usize::BITS)
+                    .map(|p| 1 << p)
+                    .take_while(|&c| c < cpu_count)
+                    .collect();
+                counts.push(cpu_count.max(1));
+                counts
+            }
+            ThreadSweep::List(counts) => counts.clone(),
+        }
+    }
+}
+
+/// One line of the scaling table, built from the mean throughput of a run.
+pub struct ScalingRow {
+    pub threads: usize,
+    pub ops_per_sec: f64,
+    pub ops_per_thread: f64,
+    /// Per-thread throughput relative to the run with the fewest threads (a single thread, normally)
+    pub efficiency: f64,
+}
+
+pub fn scaling_rows(results: &[(usize, f64)]) -> Vec<ScalingRow> {
+    let base = results
+        .iter()
+        .min_by_key(|(threads, _)| *threads)
+        .map(|(threads, ops)| ops / *threads as f64)
+        .unwrap_or(0.0);
+
+    results
+        .iter()
+        .map(|&(threads, ops_per_sec)| {
+            let ops_per_thread = ops_per_sec / threads as f64;
+            ScalingRow {
+                threads,
+                ops_per_sec,
+                ops_per_thread,
+                efficiency: if base > 0.0 {
+                    ops_per_thread / base
+                } else {
+                    0.0
+                },
+            }
+        })
+        .collect()
+}
+
+/// Lock benchmark parameter changed by a work sweep.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WorkTarget {
+    Critical,
+    Think,
+}
+
+impl WorkTarget {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WorkTarget::Critical => "critical work",
+            WorkTarget::Think => "think work",
+        }
+    }
+}
+
+/// Lengths of the critical section or of the think time to run the lock benchmarks with.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WorkSweep {
+    pub target: WorkTarget,
+    pub values: Vec<Work>,
+}
+
+impl FromStr for WorkSweep {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (target, values) = s.split_once(':').ok_or_else(|| {
+            format!(
+                "Invalid work sweep '{}', expected critical:<list> or think:<list>",
+                s
+            )
+        })?;
+        let target = match target {
+            "critical" => WorkTarget::Critical,
+            "think" => WorkTarget::Think,
+            _ => return Err(format!("Unknown work sweep target '{}'", target)),
+        };
+        let values = values
+            .split(',')
+            .map(str::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(WorkSweep { target, values })
+    }
+}
+
+impl WorkSweep {
+    pub fn apply(&self, options: &BenchOptions, work: Work) -> BenchOptions {
+        let mut options = options.clone();
+        match self.target {
+            WorkTarget::Critical => options.critical_work = work,
+            WorkTarget::Think => options.think_work = work,
+        }
+        options
+    }
+}
+
+/// One line of the work sweep table.
+pub struct WorkRow {
+    pub work: Work,
+    pub ops_per_sec: f64,
+    pub ops_per_thread: f64,
+    /// Throughput relative to the one reachable if the threads never waited for the lock,
+    /// given the calibrated duration of the critical section and of the think time
+    pub lock_free_efficiency: Option<f64>,
+}
+
+impl WorkRow {
+    pub fn new(work: Work, threads: usize, options: &BenchOptions, ops_per_sec: f64) -> Self {
+        let nanos_per_op = options.critical_work.nanos() + options.think_work.nanos();
+        let lock_free_bound = threads as f64 * 1e9 / nanos_per_op;
+        Self {
+            work,
+            ops_per_sec,
+            ops_per_thread: ops_per_sec / threads as f64,
+            lock_free_efficiency: (nanos_per_op > 0.0).then(|| ops_per_sec / lock_free_bound),
+        }
+    }
+}
+
+/// Distances between the per-thread values of the strided benchmarks, one run per stride.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StrideSweep(pub Vec<usize>);
+
+impl FromStr for StrideSweep {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "pow2" {
+            return Ok(StrideSweep((3..=12).map(|p| 1 << p).collect()));
+        }
+        let strides = s
+            .split(',')
+            .map(|stride| parse_stride(stride.trim()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(StrideSweep(strides))
+    }
+}
+
+/// Throughput of runs within this fraction of the best one are not considered slowed down by false sharing.
+const FALSE_SHARING_TOLERANCE: f64 = 0.9;
+
+/// One line of the stride table.
+pub struct StrideRow {
+    pub stride: usize,
+    pub ops_per_sec: f64,
+    pub ops_per_thread: f64,
+    /// Throughput relative to the best stride
+    pub relative: f64,
+}
+
+pub fn stride_rows(threads: usize, results: &[(usize, f64)]) -> Vec<StrideRow> {
+    let best = results.iter().map(|(_, ops)| *ops).fold(0.0, f64::max);
+    results
+        .iter()
+        .map(|&(stride, ops_per_sec)| StrideRow {
+            stride,
+            ops_per_sec,
+            ops_per_thread: ops_per_sec / threads as f64,
+            relative: if best > 0.0 { ops_per_sec / best } else { 0.0 },
+        })
+        .collect()
+}
+
+/// Smallest stride from which no larger stride is slowed down by false sharing, i.e. the
+/// effective size of the cache line, or of the line pair when the adjacent-line prefetcher is active.
+pub fn false_sharing_granularity(rows: &[StrideRow]) -> Option<usize> {
+    let mut rows: Vec<_> = rows.iter().collect();
+    rows.sort_by_key(|row| row.stride);
+    let last_slow = rows
+        .iter()
+        .rposition(|row| row.relative < FALSE_SHARING_TOLERANCE);
+    match last_slow {
+        Some(index) => rows.get(index + 1).map(|row| row.stride),
+        None => rows.first().map(|row| row.stride),
+    }
+}
+
+/// Memory chased by each thread of the pointer-chasing benchmark, one run per size.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WorkingSetSweep {
+    /// Powers of two from 4K to 4G, within the memory budget
+    Pow2,
+    List(Vec<u64>),
+}
+
+impl WorkingSetSweep {
+    /// Sizes of the sweep, the powers of two stopping before the buffers of all the threads
+    /// would take more than `memory_budget` bytes.
+    pub fn sizes(&self, threads: usize, memory_budget: u64) -> Vec<u64> {
+        match self {
+            WorkingSetSweep::Pow2 => (12..=32)
+                .map(|p| 1u64 << p)
+                .take_while(|size| size.saturating_mul(threads as u64) <= memory_budget)
+                .collect(),
+            WorkingSetSweep::List(sizes) => sizes.clone(),
+        }
+    }
+}
+
+impl FromStr for WorkingSetSweep {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "pow2" {
+            return Ok(WorkingSetSweep::Pow2);
+        }
+        let sizes = s
+            .split(',')
+            .map(|size| parse_lines_size(size.trim()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(WorkingSetSweep::List(sizes))
+    }
+}
+
+/// Memory the pow2 working set sweep may allocate, half of the physical memory.
+pub fn working_set_budget() -> u64 {
+    let pages = unsafe { libc::sysconf(libc::_SC_PHYS_PAGES) };
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    (pages.max(0) as u64).saturating_mul(page_size.max(0) as u64) / 2
+}
+
+/// Latency of runs beyond this ratio of the previous, smaller, working set is a step to the next memory level.
+const LEVEL_STEP_RATIO: f64 = 1.5;
+
+/// One line of the working set table.
+pub struct WorkingSetRow {
+    pub size: u64,
+    pub loads_per_sec: f64,
+    /// Average latency of a load, each thread waiting for its loads one after the other
+    pub nanos_per_load: f64,
+    /// Latency relative to the previous working set
+    pub step: f64,
+}
+
+impl WorkingSetRow {
+    pub fn is_level_step(&self) -> bool {
+        self.step > LEVEL_STEP_RATIO
+    }
+}
+
+pub fn working_set_rows(threads: usize, results: &[(u64, f64)]) -> Vec<WorkingSetRow> {
+    let mut previous = None;
+    results
+        .iter()
+        .map(|&(size, loads_per_sec)| {
+            let nanos_per_load = match loads_per_sec {
+                rate if rate > 0.0 => threads as f64 * 1e9 / rate,
+                _ => 0.0,
+            };
+            let step = match previous {
+                Some(previous) if previous > 0.0 => nanos_per_load / previous,
+                _ => 1.0,
+            };
+            previous = Some(nanos_per_load);
+            WorkingSetRow {
+                size,
+                loads_per_sec,
+                nanos_per_load,
+                step,
+            }
+        })
+        .collect()
+}
+
+/// Multiples of the cpu count to run each benchmark with, after a reference run with one thread per cpu.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OversubscriptionSweep(pub Vec<usize>);
+
+/// Largest oversubscription factor, past which the runs only measure the scheduler overhead.
+const MAX_OVERSUBSCRIPTION: usize = 64;
+
+impl FromStr for OversubscriptionSweep {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let factors = s
+            .split(',')
+            .map(|f| match f.trim().trim_end_matches('x').parse::<usize>() {
+                Ok(factor) if (1..=MAX_OVERSUBSCRIPTION).contains(&factor) => Ok(factor),
+                _ => Err(format!(
+                    "Invalid oversubscription factor '{}', expected 1 to {}",
+                    f, MAX_OVERSUBSCRIPTION
+                )),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(OversubscriptionSweep(factors))
+    }
+}
+
+impl OversubscriptionSweep {
+    /// The factors to run, starting with the reference one.
+    pub fn factors(&self) -> Vec<usize> {
+        std::iter::once(1)
+            .chain(self.0.iter().copied().filter(|&f| f != 1))
+            .collect()
+    }
+}
+
+/// Mean results of a run at one oversubscription factor.
+pub struct OversubscriptionResult {
+    pub factor: usize,
+    pub threads: usize,
+    pub ops_per_sec: f64,
+    pub involuntary_switches_per_sec: f64,
+    /// Estimated rate of lock holds preempted, for the mutex benchmarks
+    pub preempted_holds_per_sec: Option<f64>,
+}
+
+/// One line of the oversubscription table.
+pub struct OversubscriptionRow {
+    pub result: OversubscriptionResult,
+    /// Throughput relative to the run with one thread per cpu
+    pub relative: f64,
+}
+
+pub fn oversubscription_rows(results: Vec<OversubscriptionResult>) -> Vec<OversubscriptionRow> {
+    let reference = results
+        .iter()
+        .find(|r| r.factor == 1)
+        .map_or(0.0, |r| r.ops_per_sec);
+    results
+        .into_iter()
+        .map(|result| OversubscriptionRow {
+            relative: if reference > 0.0 {
+                result.ops_per_sec / reference
+            } else {
+                0.0
+            },
+            result,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::benchmarks::work::Work;
+    use crate::sweep::{
+        false_sharing_granularity, oversubscription_rows, scaling_rows, stride_rows,
+        working_set_rows, OversubscriptionResult, OversubscriptionSweep, StrideSweep, ThreadSweep,
+        WorkSweep, WorkTarget, WorkingSetSweep,
+    };
+
+    #[test]
+    fn parse() {
+        assert_eq!("pow2".parse(), Ok(ThreadSweep::Pow2));
+        assert_eq!("1, 2,8".parse(), Ok(ThreadSweep::List(vec![1, 2, 8])));
+        assert!("1,0".parse::<ThreadSweep>().is_err());
+        assert!("1,x".parse::<ThreadSweep>().is_err());
+        assert_eq!(ThreadSweep::Pow2.thread_counts(6), vec![1, 2, 4, 6]);
+        assert_eq!(ThreadSweep::Pow2.thread_counts(8), vec![1, 2, 4, 8]);
+    }
+
+    #[test]
+    fn efficiency() {
+        let rows = scaling_rows(&[(1, 100.0), (2, 150.0), (4, 400.0)]);
+        assert_eq!(rows[1].ops_per_thread, 75.0);
+        assert_eq!(rows[1].efficiency, 0.75);
+        assert_eq!(rows[2].efficiency, 1.0);
+    }
+
+    #[test]
+    fn work_sweep() {
+        let sweep: WorkSweep = "think:0,100,2us".parse().unwrap();
+        assert_eq!(sweep.target, WorkTarget::Think);
+        assert_eq!(
+            sweep.values,
+            vec![Work::Spins(0), Work::Spins(100), Work::Nanos(2000)]
+        );
+        assert!("inside:10".parse::<WorkSweep>().is_err());
+        assert!("critical:10x".parse::<WorkSweep>().is_err());
+        assert!("critical:18446744073709552ms".parse::<WorkSweep>().is_err());
+    }
+
+    #[test]
+    fn stride_granularity() {
+        let sweep: StrideSweep = "pow2".parse().unwrap();
+        assert_eq!(sweep.0.first(), Some(&8));
+        assert_eq!(sweep.0.last(), Some(&4096));
+        assert!("12".parse::<StrideSweep>().is_err());
+
+        let rows = stride_rows(
+            2,
+            &[(8, 10.0), (32, 12.0), (64, 60.0), (128, 98.0), (256, 100.0)],
+        );
+        assert_eq!(rows[4].ops_per_thread, 50.0);
+        assert_eq!(false_sharing_granularity(&rows), Some(128));
+    }
+
+    #[test]
+    fn working_set_levels() {
+        let sweep: WorkingSetSweep = "pow2".parse().unwrap();
+        let sizes = sweep.sizes(1, u64::MAX);
+        assert_eq!(sizes.first(), Some(&4096));
+        assert_eq!(sizes.last(), Some(&(4 << 30)));
+        assert_eq!(sweep.sizes(4, 1 << 30).last(), Some(&(256 << 20)));
+        let list = WorkingSetSweep::List(vec![4096, 1 << 20]);
+        assert_eq!("4K, 1M".parse(), Ok(list.clone()));
+        assert_eq!(list.sizes(4, 0), vec![4096, 1 << 20]);
+        assert!("100".parse::<WorkingSetSweep>().is_err());
+
+        let rows = working_set_rows(2, &[(4096, 2e9), (8192, 1.8e9), (1 << 20, 4e8)]);
+        assert_eq!(rows[0].nanos_per_load, 1.0);
+        assert_eq!(rows[2].nanos_per_load, 5.0);
+        assert!(!rows[1].is_level_step());
+        assert!(rows[2].is_level_step());
+    }
+
+    #[test]
+    fn oversubscription() {
+        let sweep: OversubscriptionSweep = "2x,4,8".parse().unwrap();
+        assert_eq!(sweep.factors(), vec![1, 2, 4, 8]);
+        assert!("0".parse::<OversubscriptionSweep>().is_err());
+        assert!("65".parse::<OversubscriptionSweep>().is_err());
+
+        let result = |factor, ops_per_sec| OversubscriptionResult {
+            factor,
+            threads: factor * 4,
+            ops_per_sec,
+            involuntary_switches_per_sec: 0.0,
+            preempted_holds_per_sec: None,
+        };
+        let rows = oversubscription_rows(vec![result(1, 100.0), result(2, 40.0)]);
+        assert_eq!(rows[0].relative, 1.0);
+        assert_eq!(rows[1].relative, 0.4);
+    }
+}
@@ -0,0 +1,137 @@
+use std::sync::OnceLock;
+
+/// Cumulative cpu time and context switches of a kernel thread, with its scheduler state when it was read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TaskTimes {
+    pub user_ticks: u64,
+    pub system_ticks: u64,
+    pub state: char,
+    /// Switches away from the thread because it blocked
+    pub voluntary_switches: u64,
+    /// Switches away from the thread because it was preempted
+    pub involuntary_switches: u64,
+}
+
+/// Cpu utilisation of one worker over an interval, and its scheduler state at the end of it.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ThreadCpu {
+    pub user: f64,
+    pub system: f64,
+    pub voluntary_switches_per_sec: f64,
+    pub involuntary_switches_per_sec: f64,
+    /// `R` running, `S` sleeping, `D` waiting on io..., `?` when the thread could not be read
+    pub state: char,
+}
+
+impl ThreadCpu {
+    pub fn between(previous: &TaskTimes, current: &TaskTimes, secs: f64) -> Self {
+        let ticks_per_sec = clock_ticks_per_sec() * secs;
+        Self {
+            user: current.user_ticks.saturating_sub(previous.user_ticks) as f64 / ticks_per_sec,
+            system: current.system_ticks.saturating_sub(previous.system_ticks) as f64
+                / ticks_per_sec,
+            voluntary_switches_per_sec: current
+                .voluntary_switches
+                .saturating_sub(previous.voluntary_switches)
+                as f64
+                / secs,
+            involuntary_switches_per_sec: current
+                .involuntary_switches
+                .saturating_sub(previous.involuntary_switches)
+                as f64
+                / secs,
+            state: current.state,
+        }
+    }
+
+    pub fn unknown() -> Self {
+        Self {
+            state: '?',
+            ..Default::default()
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.state == 'R'
+    }
+
+    pub fn is_sleeping(&self) -> bool {
+        matches!(self.state, 'S' | 'D')
+    }
+}
+
+pub fn current_tid() -> i32 {
+    unsafe { libc::gettid() }
+}
+
+pub fn read_task_times(tid: i32) -> Option<TaskTimes> {
+    let stat = std::fs::read_to_string(format!("/proc/self/task/{}/stat", tid)).ok()?;
+    let status = std::fs::read_to_string(format!("/proc/self/task/{}/status", tid)).ok()?;
+    let mut times = parse_task_stat(&stat)?;
+    (times.voluntary_switches, times.involuntary_switches) = parse_task_switches(&status)?;
+    Some(times)
+}
+
+/// Parses `/proc/<pid>/task/<tid>/stat`, whose second field is the thread name in
+/// parentheses, possibly containing spaces: the following fields start after the last `)`.
+fn parse_task_stat(stat: &str) -> Option<TaskTimes> {
+    let mut fields = stat[stat.rfind(')')? + 1..].split_whitespace();
+    // Fields 3 (state), then 14 and 15 (utime and stime), counted from 1 as in proc(5)
+    let state = fields.next()?.chars().next()?;
+    let mut fields = fields.skip(10);
+    Some(TaskTimes {
+        state,
+        user_ticks: fields.next()?.parse().ok()?,
+        system_ticks: fields.next()?.parse().ok()?,
+        voluntary_switches: 0,
+        involuntary_switches: 0,
+    })
+}
+
+/// Parses the voluntary and involuntary context switches out of `/proc/<pid>/task/<tid>/status`.
+fn parse_task_switches(status: &str) -> Option<(u64, u64)> {
+    let field = |name: &str| {
+        status
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))?
+            .trim()
+            .parse()
+            .ok()
+    };
+    Some((
+        field("voluntary_ctxt_switches")?,
+        field("nonvoluntary_ctxt_switches")?,
+    ))
+}
+
+fn clock_ticks_per_sec() -> f64 {
+    static TICKS: OnceLock<f64> = OnceLock::new();
+    *TICKS.get_or_init(|| unsafe { libc::sysconf(libc::_SC_CLK_TCK) } as f64)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::thread_stats::{
+        current_tid, parse_task_stat, parse_task_switches, read_task_times, TaskTimes,
+    };
+
+    #[test]
+    fn parse() {
+        let stat = "1234 (rust test (1)) S 1 1234 1234 0 -1 4194624 96 0 0 0 37 5 0 0 20 0 1 0";
+        assert_eq!(
+            parse_task_stat(stat),
+            Some(TaskTimes {
+                user_ticks: 37,
+                system_ticks: 5,
+                state: 'S',
+                voluntary_switches: 0,
+                involuntary_switches: 0,
+            })
+        );
+        let status = "Name:\tx\nState:\tS (sleeping)\nvoluntary_ctxt_switches:\t150\n\
+                      nonvoluntary_ctxt_switches:\t3\n";
+        assert_eq!(parse_task_switches(status), Some((150, 3)));
+        assert_eq!(parse_task_stat("1234 (x) R 1"), None);
+        assert_eq!(read_task_times(current_tid()).map(|t| t.state), Some('R'));
+    }
+}
@@ -1,45 +1,228 @@
-use std::os::linux::raw::stat;
+use crate::affinity::pin_current_thread;
+use crate::harness::RunShared;
+use crate::metrics::{MetricSample, Metrics};
+use crate::report::{Reporter, RunInfo};
+use crate::stats::jain_fairness;
+use crate::thread_stats::{read_task_times, ThreadCpu};
+use std::sync::Arc;
+use std::thread::JoinHandle;
 use std::time::{Duration, Instant};
 
-static mut TRACK_FN: Option<Box<dyn Fn() -> u128>> = None;
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(1000);
 
-pub fn set_fn(f: impl Fn() -> u128 + 'static) {
-    unsafe {
-        TRACK_FN = Some(Box::new(f));
+/// One interval measured by the tracking thread.
+#[derive(Clone, Debug)]
+pub struct Sample {
+    pub cpu_usage: f64,
+    pub system_usage: f64,
+    /// Operations per second of all the workers during this interval
+    pub ops_per_sec: f64,
+    /// Operations per second since the end of the warm-up
+    pub cumulative_ops_per_sec: f64,
+    /// Operations per second of each worker during this interval
+    pub thread_ops_per_sec: Vec<f64>,
+    /// Benchmark-specific counters, as rates per second, and gauges
+    pub metrics: Vec<MetricSample>,
+    /// Cpu utilisation and state of each worker thread, from /proc/self/task
+    pub thread_cpu: Vec<ThreadCpu>,
+}
+
+impl Sample {
+    pub fn total_usage(&self) -> f64 {
+        self.cpu_usage + self.system_usage
+    }
+
+    /// Jain's fairness index of the per-thread throughput, 1 when all threads progress equally.
+    pub fn fairness(&self) -> f64 {
+        jain_fairness(&self.thread_ops_per_sec)
+    }
+
+    /// Workers scheduled or running on a cpu when the interval ended.
+    pub fn threads_running(&self) -> usize {
+        self.thread_cpu.iter().filter(|t| t.is_running()).count()
+    }
+
+    /// Workers blocked in the kernel, for instance waiting on a futex, when the interval ended.
+    pub fn threads_sleeping(&self) -> usize {
+        self.thread_cpu.iter().filter(|t| t.is_sleeping()).count()
+    }
+
+    /// Context switches of all the workers because they blocked, per second.
+    pub fn voluntary_switches_per_sec(&self) -> f64 {
+        self.thread_cpu
+            .iter()
+            .map(|t| t.voluntary_switches_per_sec)
+            .sum()
+    }
+
+    /// Context switches of all the workers because they were preempted, per second.
+    pub fn involuntary_switches_per_sec(&self) -> f64 {
+        self.thread_cpu
+            .iter()
+            .map(|t| t.involuntary_switches_per_sec)
+            .sum()
+    }
+
+    pub fn min_thread_ops_per_sec(&self) -> f64 {
+        self.thread_ops_per_sec
+            .iter()
+            .copied()
+            .reduce(f64::min)
+            .unwrap_or(0.0)
+    }
+
+    pub fn max_thread_ops_per_sec(&self) -> f64 {
+        self.thread_ops_per_sec
+            .iter()
+            .copied()
+            .reduce(f64::max)
+            .unwrap_or(0.0)
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct TrackingOptions {
+    /// Time the workers run before the first sample, excluded from the results
+    pub warmup: Duration,
+    /// End the run, stopping all the workers, after this time
+    pub duration: Option<Duration>,
+    /// End the run after this many samples
+    pub samples: Option<usize>,
+    /// Pin the tracking thread to this cpu
+    pub cpu: Option<usize>,
+}
+
+impl TrackingOptions {
+    fn reached(&self, elapsed: Duration, samples: usize) -> bool {
+        self.duration.is_some_and(|d| elapsed >= d) || self.samples.is_some_and(|s| samples >= s)
     }
 }
 
-pub fn start_tracking() {
-    std::thread::spawn(|| {
-        let now = Instant::now();
-        let mut last_stats = simple_process_stats::ProcessStats::get().unwrap();
-        let mut last_time = now.elapsed();
-
-        loop {
-            std::thread::sleep(Duration::from_millis(1000));
-
-            let stats = simple_process_stats::ProcessStats::get().unwrap();
-            let time = now.elapsed();
-
-            let delta = time - last_time;
-            let cpu_time = (stats.cpu_time_user - last_stats.cpu_time_user).as_secs_f64()
-                / (delta.as_secs_f64());
-            let sys_time = (stats.cpu_time_kernel - last_stats.cpu_time_kernel).as_secs_f64()
-                / (delta.as_secs_f64());
-            println!(
-                "Cpu usage: {:.2} System usage: {:.2} Tot usage: {:.2} {:.2}M/s",
-                cpu_time,
-                sys_time,
-                cpu_time + sys_time,
-                unsafe {
-                    TRACK_FN.as_ref().map(|f| f()).unwrap_or(0) as f64
-                        / now.elapsed().as_secs_f64()
-                        / (1024.0 * 1024.0)
-                }
-            );
-
-            last_stats = stats;
-            last_time = time;
+/// Samples the run until it stops. On an error, the workers are stopped too.
+pub fn start_tracking(
+    run: RunInfo,
+    reporter: Arc<Reporter>,
+    shared: RunShared,
+    options: TrackingOptions,
+) -> JoinHandle<Result<Vec<Sample>, String>> {
+    std::thread::spawn(move || {
+        let stop = shared.stop.clone();
+        let result = track(&run, &reporter, shared, options);
+        if result.is_err() {
+            stop.stop();
+        }
+        result
+    })
+}
+
+fn track(
+    run: &RunInfo,
+    reporter: &Reporter,
+    shared: RunShared,
+    options: TrackingOptions,
+) -> Result<Vec<Sample>, String> {
+    let RunShared {
+        stop,
+        counters,
+        latencies,
+        metrics,
+        setup,
+    } = shared;
+    if let Some(cpu) = options.cpu {
+        pin_current_thread(cpu)
+            .map_err(|err| format!("Cannot pin the tracking thread to cpu {}: {}", cpu, err))?;
+    }
+
+    setup.wait();
+    std::thread::sleep(options.warmup);
+    latencies.start_recording();
+
+    let now = Instant::now();
+    let first_counts = counters.snapshot();
+    let mut last_stats = process_stats()?;
+    let mut last_time = now.elapsed();
+    let mut last_counts = first_counts.clone();
+    let mut last_metrics = metrics.snapshot();
+    let read_tasks = || {
+        counters
+            .thread_ids()
+            .into_iter()
+            .map(|tid| tid.and_then(read_task_times))
+            .collect::<Vec<_>>()
+    };
+    let mut last_tasks = read_tasks();
+    let mut samples = vec![];
+
+    while !stop.is_stopped() {
+        let interval = match options.duration {
+            Some(duration) => SAMPLE_INTERVAL.min(duration.saturating_sub(last_time)),
+            None => SAMPLE_INTERVAL,
+        };
+        std::thread::sleep(interval);
+
+        let stats = process_stats()?;
+        let counts = counters.snapshot();
+        let metric_values = metrics.snapshot();
+        let tasks = read_tasks();
+        let time = now.elapsed();
+
+        let delta = time - last_time;
+        let count_delta = |previous: &[u64]| {
+            counts
+                .iter()
+                .enumerate()
+                .map(|(i, count)| count - previous.get(i).unwrap_or(&0))
+                .collect::<Vec<_>>()
+        };
+        let interval_counts = count_delta(&last_counts);
+        let cpu_time =
+            (stats.cpu_time_user - last_stats.cpu_time_user).as_secs_f64() / (delta.as_secs_f64());
+        let sys_time = (stats.cpu_time_kernel - last_stats.cpu_time_kernel).as_secs_f64()
+            / (delta.as_secs_f64());
+        let sample = Sample {
+            cpu_usage: cpu_time,
+            system_usage: sys_time,
+            ops_per_sec: interval_counts.iter().sum::<u64>() as f64 / delta.as_secs_f64(),
+            cumulative_ops_per_sec: count_delta(&first_counts).iter().sum::<u64>() as f64
+                / time.as_secs_f64(),
+            thread_ops_per_sec: interval_counts
+                .iter()
+                .map(|&count| count as f64 / delta.as_secs_f64())
+                .collect(),
+            metrics: Metrics::interval(&last_metrics, metric_values.clone(), delta.as_secs_f64()),
+            thread_cpu: tasks
+                .iter()
+                .enumerate()
+                .map(
+                    |(i, task)| match (last_tasks.get(i).copied().flatten(), task) {
+                        (Some(last), Some(task)) => {
+                            ThreadCpu::between(&last, task, delta.as_secs_f64())
+                        }
+                        _ => ThreadCpu::unknown(),
+                    },
+                )
+                .collect(),
+        };
+        reporter
+            .sample(run, &sample)
+            .map_err(|err| format!("Cannot write the report: {}", err))?;
+        samples.push(sample);
+
+        last_stats = stats;
+        last_time = time;
+        last_counts = counts;
+        last_metrics = metric_values;
+        last_tasks = tasks;
+
+        if options.reached(time, samples.len()) {
+            stop.stop();
         }
-    });
+    }
+
+    Ok(samples)
+}
+
+fn process_stats() -> Result<simple_process_stats::ProcessStats, String> {
+    simple_process_stats::ProcessStats::get()
+        .map_err(|err| format!("Cannot read the process cpu times: {}", err))
 }